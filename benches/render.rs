@@ -0,0 +1,83 @@
+//! Parse and render timings for the jq manual fixture.
+//!
+//! Run with `cargo bench`. Under `cargo test` each case runs once, just to
+//! prove it still works.
+//!
+//! The `before borrowing` case is the baseline: an owned document, with
+//! every list item's blocks cloned on the way through the renderer, as they
+//! were before `Document` borrowed its text.
+
+use std::hint::black_box;
+use std::io;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 200;
+
+fn bench<F: FnMut()>(label: &str, iterations: u32, mut run: F) {
+    run();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        run();
+    }
+    let per_iter = start.elapsed() / iterations;
+    println!("{label:<34} {:>10.1?}/iter", per_iter);
+}
+
+/// The clone the renderer used to make of each list item's blocks, nested
+/// lists included, before writing them.
+fn clone_list_items(blocks: &[mandate::Block<'_>]) {
+    for block in blocks {
+        match block {
+            mandate::Block::List { items, .. } => {
+                for item in items {
+                    clone_list_items(black_box(&item.blocks.clone()));
+                }
+            }
+            mandate::Block::FootnoteDefinition { blocks, .. } => clone_list_items(blocks),
+            mandate::Block::DefinitionList { items } => {
+                for item in items {
+                    clone_list_items(&item.blocks);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() {
+    let iterations = if std::env::args().any(|arg| arg == "--bench") {
+        ITERATIONS
+    } else {
+        1
+    };
+    let yaml = include_str!("../tests/fixtures/manual.yml");
+    let markdown = mandate::convert_yaml_to_markdown(yaml).expect("fixture converts");
+    let options = mandate::ManpageOptions::new("jq", "1", "jq Manual", None, None);
+
+    bench("parse_markdown (borrowed)", iterations, || {
+        black_box(mandate::parse_markdown(black_box(&markdown)).expect("parse"));
+    });
+    bench("parse_markdown (owned)", iterations, || {
+        let document = mandate::parse_markdown(black_box(&markdown)).expect("parse");
+        black_box(document.into_owned());
+    });
+
+    let document = mandate::parse_markdown(&markdown).expect("parse");
+    bench("render_roff_to", iterations, || {
+        mandate::render_roff_to(black_box(&document), &options, io::sink()).expect("render");
+    });
+    bench("parse + render", iterations, || {
+        let document = mandate::parse_markdown(black_box(&markdown)).expect("parse");
+        mandate::render_roff_to(&document, &options, io::sink()).expect("render");
+    });
+    bench("parse + render (before borrowing)", iterations, || {
+        let document = mandate::parse_markdown(black_box(&markdown))
+            .expect("parse")
+            .into_owned();
+        clone_list_items(&document.blocks);
+        mandate::render_roff_to(&document, &options, io::sink()).expect("render");
+    });
+    bench("convert_yaml_to_roff", iterations, || {
+        black_box(mandate::convert_yaml_to_roff(black_box(yaml), &options).expect("convert"));
+    });
+}
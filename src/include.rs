@@ -0,0 +1,411 @@
+//! Include directives, resolved before anything is parsed.
+//!
+//! Markdown includes a file with a line of its own reading
+//! `<!-- include: path -->`. YAML takes a file's text as a string with
+//! `body: !include path`, or merges a YAML file into the surrounding mapping
+//! with an `include: path` key (in a list, an included list is spliced in
+//! instead). Paths are relative to the including file, then to each
+//! directory on the search path.
+
+use crate::variables::split_front_matter;
+use crate::{InputKind, MandateError, Result, input_kind};
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+#[derive(Debug, Clone, Default)]
+/// Where included files are looked for.
+pub struct SourceOptions {
+    /// Directories searched in order when an include is not found next to
+    /// the file that includes it.
+    pub search_path: Vec<PathBuf>,
+}
+
+impl SourceOptions {
+    /// Options with an empty search path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a directory to the end of the search path.
+    pub fn with_search_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.search_path.push(dir.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A manual with its includes spliced in.
+pub struct ResolvedSource {
+    /// The text, ready for the Markdown or YAML parser.
+    pub text: String,
+    /// Every file that was included, directly or not, in the order read.
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Splice the includes in `source` into place.
+///
+/// `origin` is the file `source` came from, or `None` for stdin (whose
+/// includes are relative to the current directory). With
+/// [`InputKind::Auto`], anything that loads as a YAML mapping is treated as
+/// YAML and the rest as Markdown. Text without the word `include` is
+/// returned untouched.
+pub fn resolve_includes(
+    source: &str,
+    origin: Option<&Path>,
+    kind: InputKind,
+    options: &SourceOptions,
+) -> Result<ResolvedSource> {
+    let mut includer = Includer {
+        options,
+        stack: origin.map(canonical).into_iter().collect(),
+        dependencies: Vec::new(),
+    };
+    let origin = origin.unwrap_or(Path::new("<stdin>"));
+    let text = if !source.contains("include") {
+        source.to_string()
+    } else if is_yaml(source, kind) {
+        includer.yaml_document(source, origin)?
+    } else {
+        includer.markdown(source, origin)?
+    };
+    Ok(ResolvedSource {
+        text,
+        dependencies: includer.dependencies,
+    })
+}
+
+/// Whether `source` should be treated as a YAML manual.
+pub(crate) fn is_yaml(source: &str, kind: InputKind) -> bool {
+    match kind {
+        InputKind::Yaml => true,
+        InputKind::Markdown => false,
+        // `<!-- include: x -->` and `{{program}}` on a line of their own are
+        // valid YAML mappings too, and so is the first document of a page
+        // that starts with front matter.
+        InputKind::Auto => {
+            split_front_matter(source).is_none()
+                && YamlLoader::load_from_str(source).is_ok_and(|docs| match docs.as_slice() {
+                    [Yaml::Hash(map)] => map
+                        .keys()
+                        .all(|key| key.as_str().is_some_and(|key| !key.starts_with("<!--"))),
+                    _ => false,
+                })
+        }
+    }
+}
+
+/// Write a resolved YAML manual back out as text for the parser.
+pub(crate) fn emit_yaml(root: &Yaml) -> Result<String> {
+    let mut out = String::new();
+    let mut emitter = YamlEmitter::new(&mut out);
+    emitter.multiline_strings(true);
+    emitter
+        .dump(root)
+        .map_err(|err| MandateError::Yaml(format!("{err:?}")))?;
+    out.push('\n');
+    Ok(out)
+}
+
+struct Includer<'o> {
+    options: &'o SourceOptions,
+    /// Files being resolved, outermost first, for cycle detection.
+    stack: Vec<PathBuf>,
+    dependencies: Vec<PathBuf>,
+}
+
+impl Includer<'_> {
+    /// Replace include lines outside code fences with the file they name.
+    fn markdown(&mut self, source: &str, origin: &Path) -> Result<String> {
+        let mut out = String::with_capacity(source.len());
+        let mut fences = Fences::default();
+        for (idx, line) in source.split_inclusive('\n').enumerate() {
+            let trimmed = line.trim();
+            if !fences.contains(trimmed) {
+                if let Some(target) = include_comment(trimmed) {
+                    let (path, text) = self.read(target, origin, idx + 1)?;
+                    let text = self.nested(&path, |includer| {
+                        if input_kind(&path.to_string_lossy()) == InputKind::Markdown {
+                            includer.markdown(&text, &path)
+                        } else {
+                            Ok(text.clone())
+                        }
+                    })?;
+                    out.push_str(&text);
+                    if !text.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    continue;
+                }
+            }
+            out.push_str(line);
+        }
+        Ok(out)
+    }
+
+    /// Resolve a YAML manual and write it back out as YAML text.
+    fn yaml_document(&mut self, source: &str, origin: &Path) -> Result<String> {
+        let root = self.yaml_value(source, origin)?;
+        emit_yaml(&root).map_err(|err| match err {
+            MandateError::Yaml(msg) => MandateError::Yaml(format!("{}: {msg}", origin.display())),
+            err => err,
+        })
+    }
+
+    /// Load YAML with `!include` scalars read in, then merge `include:` keys.
+    fn yaml_value(&mut self, source: &str, origin: &Path) -> Result<Yaml> {
+        // A plain load first, for its error reporting; the receiver below
+        // cannot see the loader's errors.
+        YamlLoader::load_from_str(source)
+            .map_err(|err| MandateError::Yaml(format!("{}: {err}", origin.display())))?;
+        let mut receiver = IncludeReceiver {
+            includer: self,
+            origin,
+            loader: YamlLoader::default(),
+            error: None,
+        };
+        Parser::new_from_str(source)
+            .load(&mut receiver, false)
+            .map_err(|err| MandateError::Yaml(format!("{}: {err}", origin.display())))?;
+        if let Some(err) = receiver.error {
+            return Err(err);
+        }
+        let root = receiver
+            .loader
+            .documents()
+            .first()
+            .cloned()
+            .unwrap_or(Yaml::Null);
+        self.expand(root, source, origin)
+    }
+
+    /// Merge `include:` keys and resolve Markdown includes in strings.
+    fn expand(&mut self, value: Yaml, source: &str, origin: &Path) -> Result<Yaml> {
+        match value {
+            Yaml::Hash(map) => {
+                let include = Yaml::String("include".to_string());
+                let mut merged = yaml_rust2::yaml::Hash::new();
+                if let Some(targets) = map.get(&include) {
+                    for target in include_targets(targets, origin)? {
+                        match self.include_yaml(&target, source, origin)? {
+                            Yaml::Hash(included) => merged.extend(included),
+                            _ => {
+                                return Err(MandateError::Include(format!(
+                                    "{}:{}: include '{target}': expected a YAML mapping",
+                                    origin.display(),
+                                    line_of(source, &target)
+                                )));
+                            }
+                        }
+                    }
+                }
+                for (key, value) in map {
+                    if key != include {
+                        merged.insert(key, self.expand(value, source, origin)?);
+                    }
+                }
+                Ok(Yaml::Hash(merged))
+            }
+            Yaml::Array(items) => {
+                let mut out = Vec::with_capacity(items.len());
+                for item in items {
+                    match lone_include(&item) {
+                        Some(targets) => {
+                            for target in include_targets(targets, origin)? {
+                                match self.include_yaml(&target, source, origin)? {
+                                    Yaml::Array(included) => out.extend(included),
+                                    other => out.push(other),
+                                }
+                            }
+                        }
+                        None => out.push(self.expand(item, source, origin)?),
+                    }
+                }
+                Ok(Yaml::Array(out))
+            }
+            Yaml::String(text) if text.contains("<!--") => {
+                Ok(Yaml::String(self.markdown(&text, origin)?))
+            }
+            other => Ok(other),
+        }
+    }
+
+    fn include_yaml(&mut self, target: &str, source: &str, origin: &Path) -> Result<Yaml> {
+        let (path, text) = self.read(target, origin, line_of(source, target))?;
+        self.nested(&path, |includer| includer.yaml_value(&text, &path))
+    }
+
+    /// The text for a `!include` scalar; Markdown gets its own includes
+    /// resolved.
+    fn include_text(&mut self, target: &str, origin: &Path, line: usize) -> Result<String> {
+        let (path, text) = self.read(target, origin, line)?;
+        if input_kind(&path.to_string_lossy()) != InputKind::Markdown {
+            return Ok(text);
+        }
+        self.nested(&path, |includer| includer.markdown(&text, &path))
+    }
+
+    /// Find and read `target`, included from line `line` of `origin`.
+    fn read(&mut self, target: &str, origin: &Path, line: usize) -> Result<(PathBuf, String)> {
+        let location = format!("{}:{line}", origin.display());
+        let path = self.locate(target, origin).ok_or_else(|| {
+            MandateError::Include(format!("{location}: include '{target}': file not found"))
+        })?;
+        let canonical = canonical(&path);
+        if self.stack.contains(&canonical) {
+            let chain = self
+                .stack
+                .iter()
+                .chain([&canonical])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(MandateError::Include(format!(
+                "{location}: include '{target}': include cycle: {chain}"
+            )));
+        }
+        let text = fs::read_to_string(&path).map_err(|err| {
+            MandateError::Include(format!("{location}: include '{target}': {err}"))
+        })?;
+        self.dependencies.push(path.clone());
+        Ok((path, text))
+    }
+
+    /// Resolve the contents of `path` with it on the include stack.
+    fn nested<T>(
+        &mut self,
+        path: &Path,
+        resolve: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.stack.push(canonical(path));
+        let result = resolve(self);
+        self.stack.pop();
+        result
+    }
+
+    fn locate(&self, target: &str, origin: &Path) -> Option<PathBuf> {
+        let target = Path::new(target);
+        if target.is_absolute() {
+            return target.is_file().then(|| target.to_path_buf());
+        }
+        let beside = origin.parent().unwrap_or(Path::new("")).join(target);
+        std::iter::once(beside)
+            .chain(self.options.search_path.iter().map(|dir| dir.join(target)))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Reads YAML events into a [`YamlLoader`], replacing `!include path`
+/// scalars with the text of the file they name.
+struct IncludeReceiver<'i, 'o> {
+    includer: &'i mut Includer<'o>,
+    origin: &'i Path,
+    loader: YamlLoader,
+    error: Option<MandateError>,
+}
+
+impl MarkedEventReceiver for IncludeReceiver<'_, '_> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+        let event = match event {
+            Event::Scalar(target, _, anchor, Some(tag))
+                if tag.handle == "!" && tag.suffix == "include" =>
+            {
+                match self
+                    .includer
+                    .include_text(&target, self.origin, mark.line())
+                {
+                    Ok(text) => Event::Scalar(text, TScalarStyle::Literal, anchor, None),
+                    Err(err) => {
+                        self.error = Some(err);
+                        return;
+                    }
+                }
+            }
+            event => event,
+        };
+        self.loader.on_event(event, mark);
+    }
+}
+
+#[derive(Debug, Default)]
+/// Tracks whether Markdown lines are inside a ```` ``` ```` or `~~~` fence,
+/// where directives are just text.
+pub(crate) struct Fences {
+    marker: Option<&'static str>,
+}
+
+impl Fences {
+    /// Whether the (trimmed) `line` belongs to a code fence, delimiters
+    /// included. Call it on every line, in order.
+    pub(crate) fn contains(&mut self, line: &str) -> bool {
+        match self.marker {
+            Some(marker) if line.starts_with(marker) => {
+                self.marker = None;
+                true
+            }
+            Some(_) => true,
+            None => {
+                self.marker = ["```", "~~~"]
+                    .into_iter()
+                    .find(|marker| line.starts_with(marker));
+                self.marker.is_some()
+            }
+        }
+    }
+}
+
+/// The same file under any name, as far as the filesystem can tell.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The path in a `<!-- include: path -->` line.
+fn include_comment(line: &str) -> Option<&str> {
+    let body = line.strip_prefix("<!--")?.strip_suffix("-->")?.trim();
+    let target = body.strip_prefix("include:")?.trim();
+    (!target.is_empty()).then_some(target)
+}
+
+/// The value of an `include:` key: one path or a list of them.
+fn include_targets(value: &Yaml, origin: &Path) -> Result<Vec<String>> {
+    match value {
+        Yaml::String(target) => Ok(vec![target.clone()]),
+        Yaml::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Yaml::String(target) => Ok(target.clone()),
+                _ => Err(include_type_error(origin)),
+            })
+            .collect(),
+        _ => Err(include_type_error(origin)),
+    }
+}
+
+fn include_type_error(origin: &Path) -> MandateError {
+    MandateError::Include(format!(
+        "{}: 'include' takes a path or a list of paths",
+        origin.display()
+    ))
+}
+
+/// A list item that is nothing but `include: ...`.
+fn lone_include(item: &Yaml) -> Option<&Yaml> {
+    match item {
+        Yaml::Hash(map) if map.len() == 1 => map.get(&Yaml::String("include".to_string())),
+        _ => None,
+    }
+}
+
+/// Best-effort line number of an `include:` key naming `target`; the YAML
+/// loader keeps no positions for keys.
+fn line_of(source: &str, target: &str) -> usize {
+    source
+        .lines()
+        .position(|line| line.contains("include") && line.contains(target))
+        .map_or(0, |idx| idx + 1)
+}
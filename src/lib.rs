@@ -10,14 +10,29 @@
 //! # Ok::<(), mandate::MandateError>(())
 //! ```
 
+mod include;
+mod math;
+mod profile;
+mod variables;
+pub mod visit;
+
+pub use include::{ResolvedSource, SourceOptions, resolve_includes};
+pub use profile::apply_profile;
+pub use variables::substitute_variables;
+
 use jsonschema::validator_for;
-use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
 use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 use yaml_rust2::{Yaml, YamlLoader, yaml::Hash};
 
@@ -27,32 +42,122 @@ use yaml_rust2::{Yaml, YamlLoader, yaml::Hash};
 /// from disk.
 pub const BUILTIN_SCHEMA: &str = include_str!("../data/manual_schema.yml");
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Parsed representation of a manual document.
 ///
-/// It is intentionally simple: a list of blocks. No lifetimes, no magic, and
-/// absolutely no feelings about your heading hierarchy.
-pub struct Document {
-    pub blocks: Vec<Block>,
+/// It is intentionally simple: a list of blocks. Text borrows from the source
+/// whenever the parser allows it; call [`Document::into_owned`] when the
+/// document needs to outlive its input. No magic, and absolutely no feelings
+/// about your heading hierarchy.
+pub struct Document<'a> {
+    pub blocks: Vec<Block<'a>>,
 }
 
-#[derive(Debug, Clone)]
+impl Document<'_> {
+    /// Detach the document from its source text.
+    pub fn into_owned(self) -> Document<'static> {
+        Document {
+            blocks: self.blocks.into_iter().map(Block::into_owned).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 /// Block-level elements produced by the Markdown/YAML parser.
-pub enum Block {
+pub enum Block<'a> {
     /// A heading with a numeric level and inline content.
-    Heading { level: u8, content: Vec<Inline> },
+    Heading { level: u8, content: Vec<Inline<'a>> },
     /// A paragraph composed of inline nodes.
-    Paragraph(Vec<Inline>),
+    Paragraph(Vec<Inline<'a>>),
     /// An ordered or unordered list.
     List {
         kind: ListKind,
-        items: Vec<ListItem>,
+        items: Vec<ListItem<'a>>,
+    },
+    /// A fenced or indented code block. `lang` is the first word of the
+    /// fence's info string, if any.
+    CodeBlock {
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        lang: Option<Cow<'a, str>>,
+        text: Cow<'a, str>,
+    },
+    /// A thematic break (`---`).
+    Rule,
+    /// The body of a footnote (`[^label]: ...`).
+    FootnoteDefinition {
+        label: Cow<'a, str>,
+        blocks: Vec<Block<'a>>,
+    },
+    /// Display math (`$$...$$`), as TeX.
+    Math { tex: Cow<'a, str> },
+    /// Output passed through verbatim to the backend named by `format`
+    /// (a ```` ```roff ```` fence or `<!-- roff: ... -->`).
+    Raw {
+        format: Cow<'a, str>,
+        text: Cow<'a, str>,
+    },
+    /// A definition list, from HTML `<dl>`/`<dt>`/`<dd>`.
+    DefinitionList { items: Vec<Definition<'a>> },
+    /// An HTML comment (`<!-- ... -->`) on its own, without the markers.
+    Comment(Cow<'a, str>),
+    /// An instruction to mandate itself (`<!-- mandate: name args -->`).
+    Directive {
+        name: Cow<'a, str>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        args: Cow<'a, str>,
     },
-    /// A fenced code block.
-    CodeBlock { text: String },
 }
 
-#[derive(Debug, Clone)]
+impl Block<'_> {
+    /// Detach the block from its source text.
+    pub fn into_owned(self) -> Block<'static> {
+        match self {
+            Block::Heading { level, content } => Block::Heading {
+                level,
+                content: inlines_into_owned(content),
+            },
+            Block::Paragraph(inlines) => Block::Paragraph(inlines_into_owned(inlines)),
+            Block::List { kind, items } => Block::List {
+                kind,
+                items: items.into_iter().map(ListItem::into_owned).collect(),
+            },
+            Block::CodeBlock { lang, text } => Block::CodeBlock {
+                lang: lang.map(|lang| Cow::Owned(lang.into_owned())),
+                text: Cow::Owned(text.into_owned()),
+            },
+            Block::Rule => Block::Rule,
+            Block::FootnoteDefinition { label, blocks } => Block::FootnoteDefinition {
+                label: Cow::Owned(label.into_owned()),
+                blocks: blocks.into_iter().map(Block::into_owned).collect(),
+            },
+            Block::Math { tex } => Block::Math {
+                tex: Cow::Owned(tex.into_owned()),
+            },
+            Block::Raw { format, text } => Block::Raw {
+                format: Cow::Owned(format.into_owned()),
+                text: Cow::Owned(text.into_owned()),
+            },
+            Block::DefinitionList { items } => Block::DefinitionList {
+                items: items.into_iter().map(Definition::into_owned).collect(),
+            },
+            Block::Comment(text) => Block::Comment(Cow::Owned(text.into_owned())),
+            Block::Directive { name, args } => Block::Directive {
+                name: Cow::Owned(name.into_owned()),
+                args: Cow::Owned(args.into_owned()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 /// List variants supported by the renderer.
 pub enum ListKind {
     /// Bullet list.
@@ -61,34 +166,359 @@ pub enum ListKind {
     Ordered { start: u64 },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A single list item containing block content.
-pub struct ListItem {
-    pub blocks: Vec<Block>,
+pub struct ListItem<'a> {
+    pub blocks: Vec<Block<'a>>,
+    /// Task list state: `Some(true)` for `[x]`, `Some(false)` for `[ ]`,
+    /// `None` for an ordinary item.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub checked: Option<bool>,
 }
 
-#[derive(Debug, Clone)]
+impl ListItem<'_> {
+    /// Detach the list item from its source text.
+    pub fn into_owned(self) -> ListItem<'static> {
+        ListItem {
+            blocks: self.blocks.into_iter().map(Block::into_owned).collect(),
+            checked: self.checked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A term and its definition in a [`Block::DefinitionList`].
+pub struct Definition<'a> {
+    pub term: Vec<Inline<'a>>,
+    pub blocks: Vec<Block<'a>>,
+}
+
+impl Definition<'_> {
+    /// Detach the definition from its source text.
+    pub fn into_owned(self) -> Definition<'static> {
+        Definition {
+            term: inlines_into_owned(self.term),
+            blocks: self.blocks.into_iter().map(Block::into_owned).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 /// Inline-level elements produced by the Markdown parser.
-pub enum Inline {
+pub enum Inline<'a> {
     /// Plain text.
-    Text(String),
+    Text(Cow<'a, str>),
     /// Inline code span.
-    Code(String),
+    Code(Cow<'a, str>),
     /// Emphasized content.
-    Emphasis(Vec<Inline>),
+    Emphasis(Vec<Inline<'a>>),
     /// Strong (bold) content.
-    Strong(Vec<Inline>),
+    Strong(Vec<Inline<'a>>),
+    /// Struck-through content (`~~old~~`).
+    Strikethrough(Vec<Inline<'a>>),
+    /// Superscript content (`^2^`).
+    Superscript(Vec<Inline<'a>>),
+    /// Subscript content (`~2~`).
+    Subscript(Vec<Inline<'a>>),
     /// A link with optional title.
     Link {
-        url: String,
-        title: Option<String>,
-        content: Vec<Inline>,
+        url: Cow<'a, str>,
+        title: Option<Cow<'a, str>>,
+        content: Vec<Inline<'a>>,
     },
     /// A soft or hard line break.
     LineBreak(LineBreak),
+    /// A reference to the footnote with this label (`[^label]`).
+    FootnoteReference(Cow<'a, str>),
+    /// Inline math (`$...$`), as TeX.
+    Math(Cow<'a, str>),
+    /// An HTML comment inside a paragraph, without the markers.
+    Comment(Cow<'a, str>),
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Inline<'_> {
+    /// Detach the inline from its source text.
+    pub fn into_owned(self) -> Inline<'static> {
+        match self {
+            Inline::Text(text) => Inline::Text(Cow::Owned(text.into_owned())),
+            Inline::Code(text) => Inline::Code(Cow::Owned(text.into_owned())),
+            Inline::Emphasis(children) => Inline::Emphasis(inlines_into_owned(children)),
+            Inline::Strong(children) => Inline::Strong(inlines_into_owned(children)),
+            Inline::Strikethrough(children) => Inline::Strikethrough(inlines_into_owned(children)),
+            Inline::Superscript(children) => Inline::Superscript(inlines_into_owned(children)),
+            Inline::Subscript(children) => Inline::Subscript(inlines_into_owned(children)),
+            Inline::FootnoteReference(label) => {
+                Inline::FootnoteReference(Cow::Owned(label.into_owned()))
+            }
+            Inline::Math(tex) => Inline::Math(Cow::Owned(tex.into_owned())),
+            Inline::Comment(text) => Inline::Comment(Cow::Owned(text.into_owned())),
+            Inline::Link {
+                url,
+                title,
+                content,
+            } => Inline::Link {
+                url: Cow::Owned(url.into_owned()),
+                title: title.map(|title| Cow::Owned(title.into_owned())),
+                content: inlines_into_owned(content),
+            },
+            Inline::LineBreak(kind) => Inline::LineBreak(kind),
+        }
+    }
+}
+
+fn inlines_into_owned(inlines: Vec<Inline<'_>>) -> Vec<Inline<'static>> {
+    inlines.into_iter().map(Inline::into_owned).collect()
+}
+
+impl Document<'static> {
+    /// Start building a document in code.
+    ///
+    /// The builder produces the same AST that parsing the equivalent Markdown
+    /// would, so generated pages render exactly like hand-written ones.
+    ///
+    /// ```
+    /// let document = mandate::Document::builder()
+    ///     .name("mytool", "does things")
+    ///     .section("SYNOPSIS", |s| s.para("mytool [options] <file>"))
+    ///     .section("OPTIONS", |s| {
+    ///         s.option("-v, --verbose", "Talk more.")
+    ///             .option("-q, --quiet", "Talk less.")
+    ///     })
+    ///     .section("EXAMPLES", |s| s.code("mytool -v notes.txt"))
+    ///     .build();
+    /// let options = mandate::ManpageOptions::new("mytool", "1", "Mytool Manual", None, None);
+    /// let roff = mandate::render_roff(&document, &options)?;
+    /// assert!(roff.contains(".SH \"OPTIONS\""));
+    /// # Ok::<(), mandate::MandateError>(())
+    /// ```
+    pub fn builder() -> DocumentBuilder {
+        DocumentBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Fluent builder for a [`Document`]; see [`Document::builder`].
+pub struct DocumentBuilder {
+    blocks: Vec<Block<'static>>,
+}
+
+impl DocumentBuilder {
+    /// Add the level-1 heading that becomes the `NAME` section.
+    pub fn name(mut self, name: &str, description: &str) -> Self {
+        self.blocks.push(Block::Heading {
+            level: 1,
+            content: vec![Inline::Text(Cow::Owned(format!("{name} -- {description}")))],
+        });
+        self
+    }
+
+    /// Add a `.SH` section and fill it in with `build`.
+    pub fn section<F>(mut self, title: impl Into<Cow<'static, str>>, build: F) -> Self
+    where
+        F: FnOnce(SectionBuilder) -> SectionBuilder,
+    {
+        self.blocks.extend(SectionBuilder::heading(2, title, build));
+        self
+    }
+
+    /// Add an option entry at the top level; see [`SectionBuilder::option`].
+    pub fn option(
+        mut self,
+        term: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.blocks
+            .extend(option_blocks(term.into(), description.into()));
+        self
+    }
+
+    /// Add an arbitrary block.
+    pub fn block(mut self, block: Block<'static>) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    /// Finish the document.
+    pub fn build(self) -> Document<'static> {
+        Document {
+            blocks: self.blocks,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Builds the body of a section; handed to [`DocumentBuilder::section`].
+pub struct SectionBuilder {
+    blocks: Vec<Block<'static>>,
+}
+
+impl SectionBuilder {
+    fn heading<F>(level: u8, title: impl Into<Cow<'static, str>>, build: F) -> Vec<Block<'static>>
+    where
+        F: FnOnce(SectionBuilder) -> SectionBuilder,
+    {
+        let mut blocks = vec![Block::Heading {
+            level,
+            content: vec![Inline::Text(title.into())],
+        }];
+        blocks.extend(build(SectionBuilder::default()).blocks);
+        blocks
+    }
+
+    /// Add a plain-text paragraph.
+    pub fn para(mut self, text: impl Into<Cow<'static, str>>) -> Self {
+        self.blocks
+            .push(Block::Paragraph(vec![Inline::Text(text.into())]));
+        self
+    }
+
+    /// Add a paragraph made of arbitrary inlines.
+    pub fn inlines(mut self, inlines: Vec<Inline<'static>>) -> Self {
+        self.blocks.push(Block::Paragraph(inlines));
+        self
+    }
+
+    /// Add a code block. A trailing newline is added if missing, matching
+    /// what fenced code blocks parse to.
+    pub fn code(mut self, text: impl Into<Cow<'static, str>>) -> Self {
+        let mut text = text.into();
+        if !text.ends_with('\n') {
+            text.to_mut().push('\n');
+        }
+        self.blocks.push(Block::CodeBlock { lang: None, text });
+        self
+    }
+
+    /// Add a code block tagged with a language, like a ```` ```console ````
+    /// fence.
+    pub fn code_lang(
+        mut self,
+        lang: impl Into<Cow<'static, str>>,
+        text: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self = self.code(text);
+        if let Some(Block::CodeBlock { lang: slot, .. }) = self.blocks.last_mut() {
+            *slot = Some(lang.into());
+        }
+        self
+    }
+
+    /// Add a bulleted list with one plain-text paragraph per item.
+    pub fn bullets<I, T>(mut self, items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Cow<'static, str>>,
+    {
+        let items = items
+            .into_iter()
+            .map(|item| ListItem {
+                blocks: vec![Block::Paragraph(vec![Inline::Text(item.into())])],
+                checked: None,
+            })
+            .collect();
+        self.blocks.push(Block::List {
+            kind: ListKind::Unordered,
+            items,
+        });
+        self
+    }
+
+    /// Add a checklist: each item is `(done, text)`. Same shape as a
+    /// `- [x] text` task list in Markdown.
+    pub fn tasks<I, T>(mut self, items: I) -> Self
+    where
+        I: IntoIterator<Item = (bool, T)>,
+        T: Into<Cow<'static, str>>,
+    {
+        let items = items
+            .into_iter()
+            .map(|(done, item)| ListItem {
+                blocks: vec![Block::Paragraph(vec![Inline::Text(item.into())])],
+                checked: Some(done),
+            })
+            .collect();
+        self.blocks.push(Block::List {
+            kind: ListKind::Unordered,
+            items,
+        });
+        self
+    }
+
+    /// Add a horizontal rule.
+    pub fn rule(mut self) -> Self {
+        self.blocks.push(Block::Rule);
+        self
+    }
+
+    /// Add output for one backend, passed through verbatim. Use sparingly:
+    /// nothing checks that `text` is valid `format`.
+    pub fn raw(
+        mut self,
+        format: impl Into<Cow<'static, str>>,
+        text: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.blocks.push(Block::Raw {
+            format: format.into(),
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Add an option entry: `term` in bold as a tagged paragraph, followed by
+    /// its description. Same shape as `` - `term`: `` followed by a paragraph
+    /// in Markdown.
+    pub fn option(
+        mut self,
+        term: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.blocks
+            .extend(option_blocks(term.into(), description.into()));
+        self
+    }
+
+    /// Add a `.SS` subsection and fill it in with `build`.
+    pub fn subsection<F>(mut self, title: impl Into<Cow<'static, str>>, build: F) -> Self
+    where
+        F: FnOnce(SectionBuilder) -> SectionBuilder,
+    {
+        self.blocks.extend(SectionBuilder::heading(3, title, build));
+        self
+    }
+
+    /// Add an arbitrary block.
+    pub fn block(mut self, block: Block<'static>) -> Self {
+        self.blocks.push(block);
+        self
+    }
+}
+
+fn option_blocks(term: Cow<'static, str>, description: Cow<'static, str>) -> [Block<'static>; 2] {
+    [
+        Block::List {
+            kind: ListKind::Unordered,
+            items: vec![ListItem {
+                blocks: vec![Block::Paragraph(vec![
+                    Inline::Code(term),
+                    Inline::Text(Cow::Borrowed(":")),
+                ])],
+                checked: None,
+            }],
+        },
+        Block::Paragraph(vec![Inline::Text(description)]),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 /// How to treat a Markdown line break.
 pub enum LineBreak {
     /// Markdown soft break (`\n`), rendered as a space.
@@ -109,6 +539,186 @@ pub struct ManpageOptions {
     pub title: String,
     pub manual_section: Option<String>,
     pub source: Option<String>,
+    /// How non-ASCII and typographic characters are written.
+    pub charset: Charset,
+    /// Which prose hyphens become minus signs.
+    pub hyphens: HyphenPolicy,
+    /// Start every sentence on a new source line, as man-pages(7) asks.
+    pub sentence_per_line: bool,
+    /// How `~~struck~~` text is shown.
+    pub strikethrough: StrikethroughStyle,
+    /// Parse `^superscript^` and `~subscript~`. Off by default, since a
+    /// regex or a home directory path is more likely than a footnote mark.
+    pub scripts: bool,
+    /// Number links like footnotes and list their URLs under `NOTES`.
+    pub link_notes: bool,
+    /// How TeX math is rendered, or `None` to leave `$` as an ordinary
+    /// character. Prose full of `$HOME` and `$5` is not TeX.
+    pub math: Option<MathMode>,
+    /// Columns between tab stops in code blocks.
+    pub tab_width: usize,
+    /// Render consecutive code blocks in the same language as one block.
+    pub merge_code_blocks: bool,
+    /// What happens to HTML comments.
+    pub comments: CommentMode,
+    /// Values for `{{name}}` references in manual sources, overriding the
+    /// built-ins and the manual's own front matter or `meta:`.
+    pub variables: BTreeMap<String, String>,
+    /// Make a reference to an undefined variable an error instead of
+    /// leaving it in the output for someone to notice.
+    pub strict_variables: bool,
+    /// The target being rendered for, as `key=value` pairs that
+    /// `<!-- if -->` blocks and `only:` keys are checked against.
+    pub profile: BTreeMap<String, String>,
+    /// The release being documented. YAML sections and entries added after
+    /// it (`since`) or removed by it (`removed`) are left out.
+    pub target_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Character repertoire used for the roff output.
+pub enum Charset {
+    /// Map typographic characters to groff special characters (`\(em`,
+    /// `\(lq`, ...) and everything else outside ASCII to `\[uXXXX]`.
+    #[default]
+    Groff,
+    /// Plain ASCII approximations only, for troffs that predate groff's
+    /// special character names.
+    Ascii,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// How hyphens in prose are written. Code is unaffected: there every `-`
+/// is a minus sign (`\-`), because that is what people will paste.
+pub enum HyphenPolicy {
+    /// Options (`-v`, `--flag`) and negative numbers get `\-`, compound
+    /// words keep a plain hyphen, and a spaced `--` or `-` becomes an em or
+    /// en dash.
+    #[default]
+    Smart,
+    /// Every hyphen is a minus sign, as in the days before we had opinions.
+    Minus,
+}
+
+impl HyphenPolicy {
+    /// The name used to select this policy.
+    pub fn name(self) -> &'static str {
+        match self {
+            HyphenPolicy::Smart => "smart",
+            HyphenPolicy::Minus => "minus",
+        }
+    }
+}
+
+impl FromStr for HyphenPolicy {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        [HyphenPolicy::Smart, HyphenPolicy::Minus]
+            .into_iter()
+            .find(|policy| policy.name() == name)
+            .ok_or_else(|| format!("unknown hyphen policy '{name}' (expected smart or minus)"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// How struck-through text is rendered. Man pages have no strikethrough
+/// font, so we either say what it means or draw it.
+pub enum StrikethroughStyle {
+    /// Prefix the text with an italic `deprecated:`, which is what a
+    /// strikethrough in a man page almost always means.
+    #[default]
+    Marker,
+    /// Overstrike every character with a hyphen.
+    Overstrike,
+}
+
+impl StrikethroughStyle {
+    /// The name used to select this style.
+    pub fn name(self) -> &'static str {
+        match self {
+            StrikethroughStyle::Marker => "marker",
+            StrikethroughStyle::Overstrike => "overstrike",
+        }
+    }
+}
+
+impl FromStr for StrikethroughStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        [StrikethroughStyle::Marker, StrikethroughStyle::Overstrike]
+            .into_iter()
+            .find(|style| style.name() == name)
+            .ok_or_else(|| {
+                format!("unknown strikethrough style '{name}' (expected marker or overstrike)")
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// How TeX math is rendered.
+pub enum MathMode {
+    /// Italic text with Unicode symbols (ASCII words under
+    /// [`Charset::Ascii`]). Works with any troff, no preprocessor needed.
+    #[default]
+    Text,
+    /// eqn(1) input: `.EQ`/`.EN` displays and `$...$` inline. The page
+    /// starts with a `'\" e` line so man(1) runs eqn, and literal dollar
+    /// signs are written as `\(Do`.
+    Eqn,
+}
+
+impl MathMode {
+    /// The name used to select this mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            MathMode::Text => "text",
+            MathMode::Eqn => "eqn",
+        }
+    }
+}
+
+impl FromStr for MathMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        [MathMode::Text, MathMode::Eqn]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+            .ok_or_else(|| format!("unknown math mode '{name}' (expected text or eqn)"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// What happens to HTML comments in the Markdown.
+pub enum CommentMode {
+    /// Leave them out, as a browser would.
+    #[default]
+    Drop,
+    /// Keep them as roff comments (`.\"`), for whoever reads the source.
+    Roff,
+}
+
+impl CommentMode {
+    /// The name used to select this mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            CommentMode::Drop => "drop",
+            CommentMode::Roff => "roff",
+        }
+    }
+}
+
+impl FromStr for CommentMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        [CommentMode::Drop, CommentMode::Roff]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+            .ok_or_else(|| format!("unknown comment mode '{name}' (expected drop or roff)"))
+    }
 }
 
 impl ManpageOptions {
@@ -129,8 +739,110 @@ impl ManpageOptions {
             title: title.into(),
             manual_section,
             source,
+            charset: Charset::default(),
+            hyphens: HyphenPolicy::default(),
+            sentence_per_line: false,
+            strikethrough: StrikethroughStyle::default(),
+            scripts: false,
+            link_notes: false,
+            math: None,
+            tab_width: 8,
+            merge_code_blocks: true,
+            comments: CommentMode::default(),
+            variables: BTreeMap::new(),
+            strict_variables: false,
+            profile: BTreeMap::new(),
+            target_version: None,
         }
     }
+
+    /// Choose the output character repertoire.
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Choose how prose hyphens are written.
+    pub fn with_hyphens(mut self, hyphens: HyphenPolicy) -> Self {
+        self.hyphens = hyphens;
+        self
+    }
+
+    /// Put each sentence on its own source line, so troff sees sentence
+    /// ends (and adds its inter-sentence space) and diffs stay line-sized.
+    pub fn with_sentence_per_line(mut self, sentence_per_line: bool) -> Self {
+        self.sentence_per_line = sentence_per_line;
+        self
+    }
+
+    /// Choose how struck-through text is shown.
+    pub fn with_strikethrough(mut self, strikethrough: StrikethroughStyle) -> Self {
+        self.strikethrough = strikethrough;
+        self
+    }
+
+    /// Parse `^superscript^` and `~subscript~` spans.
+    pub fn with_scripts(mut self, scripts: bool) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    /// Follow link text with a `[N]` reference and list the URL as note N,
+    /// the way DocBook-generated pages do.
+    pub fn with_link_notes(mut self, link_notes: bool) -> Self {
+        self.link_notes = link_notes;
+        self
+    }
+
+    /// Parse `$...$` and `$$...$$` as TeX math, rendered as `math`.
+    pub fn with_math(mut self, math: MathMode) -> Self {
+        self.math = Some(math);
+        self
+    }
+
+    /// Set the tab stop width used when expanding tabs in code blocks.
+    /// Zero leaves tabs alone, at the mercy of troff's own tab stops.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Merge consecutive code blocks in the same language into one, as if
+    /// they had been a single fence.
+    pub fn with_merge_code_blocks(mut self, merge_code_blocks: bool) -> Self {
+        self.merge_code_blocks = merge_code_blocks;
+        self
+    }
+
+    /// Choose whether HTML comments are dropped or kept as roff comments.
+    pub fn with_comments(mut self, comments: CommentMode) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Define a `{{name}}` variable for [`substitute_variables`].
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Fail on references to undefined variables.
+    pub fn with_strict_variables(mut self, strict_variables: bool) -> Self {
+        self.strict_variables = strict_variables;
+        self
+    }
+
+    /// Set a profile value for [`apply_profile`], such as `platform=linux`.
+    pub fn with_profile(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.profile.insert(key.into(), value.into());
+        self
+    }
+
+    /// Document the release `version`, for [`apply_profile`].
+    pub fn with_target_version(mut self, version: impl Into<String>) -> Self {
+        self.target_version = Some(version.into());
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -144,6 +856,18 @@ pub enum MandateError {
     Yaml(String),
     /// Schema validation failed or could not be loaded.
     Schema(String),
+    /// Reading inputs or writing outputs failed.
+    Io(String),
+    /// An API was called without something it needs, like a `Builder`
+    /// without an input.
+    Usage(String),
+    /// A Document could not be converted to or from JSON.
+    Json(String),
+    /// An include directive could not be resolved; the message starts with
+    /// the including file and line.
+    Include(String),
+    /// A `{{name}}` reference could not be substituted.
+    Variable(String),
 }
 
 impl fmt::Display for MandateError {
@@ -153,94 +877,232 @@ impl fmt::Display for MandateError {
             MandateError::Markdown(msg) => write!(f, "markdown parse error: {msg}"),
             MandateError::Yaml(msg) => write!(f, "yaml parse error: {msg}"),
             MandateError::Schema(msg) => write!(f, "schema validation error: {msg}"),
+            MandateError::Io(msg) => write!(f, "io error: {msg}"),
+            MandateError::Usage(msg) => write!(f, "usage error: {msg}"),
+            MandateError::Json(msg) => write!(f, "ast json error: {msg}"),
+            MandateError::Include(msg) => write!(f, "include error: {msg}"),
+            MandateError::Variable(msg) => write!(f, "variable error: {msg}"),
         }
     }
 }
 
 impl Error for MandateError {}
 
+impl From<io::Error> for MandateError {
+    fn from(err: io::Error) -> Self {
+        MandateError::Io(err.to_string())
+    }
+}
+
 /// Convenience result type for Mandate operations.
 pub type Result<T> = std::result::Result<T, MandateError>;
 
 #[derive(Debug, Default)]
-struct BlockContainerFrame {
-    blocks: Vec<Block>,
-    pending_inlines: Vec<Inline>,
+struct BlockContainerFrame<'a> {
+    blocks: Vec<Block<'a>>,
+    pending_inlines: Vec<Inline<'a>>,
 }
 
-impl BlockContainerFrame {
-    fn push_inline(&mut self, inline: Inline) {
+impl<'a> BlockContainerFrame<'a> {
+    fn push_inline(&mut self, inline: Inline<'a>) {
         self.pending_inlines.push(inline);
     }
 
     fn flush_pending(&mut self) {
         if !self.pending_inlines.is_empty() {
-            let inlines = std::mem::take(&mut self.pending_inlines);
+            let mut inlines = std::mem::take(&mut self.pending_inlines);
+            tidy_html_spacing(&mut inlines);
             self.blocks.push(Block::Paragraph(inlines));
         }
     }
 
-    fn push_block(&mut self, block: Block) {
+    fn push_block(&mut self, block: Block<'a>) {
         self.flush_pending();
         self.blocks.push(block);
     }
 
-    fn finish(mut self) -> Vec<Block> {
+    fn finish(mut self) -> Vec<Block<'a>> {
         self.flush_pending();
         self.blocks
     }
 }
 
 #[derive(Debug)]
-enum Frame {
-    Document(BlockContainerFrame),
-    BlockContainer(BlockContainerFrame),
+enum Frame<'a> {
+    Document(BlockContainerFrame<'a>),
+    BlockContainer(BlockContainerFrame<'a>),
     List {
         kind: ListKind,
-        items: Vec<ListItem>,
+        items: Vec<ListItem<'a>>,
+    },
+    ListItem {
+        frame: BlockContainerFrame<'a>,
+        checked: Option<bool>,
+    },
+    FootnoteDefinition {
+        label: Cow<'a, str>,
+        frame: BlockContainerFrame<'a>,
     },
-    ListItem(BlockContainerFrame),
     Paragraph {
-        inlines: Vec<Inline>,
+        inlines: Vec<Inline<'a>>,
     },
     Heading {
         level: u8,
-        inlines: Vec<Inline>,
+        inlines: Vec<Inline<'a>>,
     },
     Emphasis {
-        inlines: Vec<Inline>,
+        inlines: Vec<Inline<'a>>,
     },
     Strong {
-        inlines: Vec<Inline>,
+        inlines: Vec<Inline<'a>>,
+    },
+    Strikethrough {
+        inlines: Vec<Inline<'a>>,
+    },
+    Superscript {
+        inlines: Vec<Inline<'a>>,
+    },
+    Subscript {
+        inlines: Vec<Inline<'a>>,
     },
     Link {
-        url: String,
-        title: Option<String>,
-        inlines: Vec<Inline>,
+        url: Cow<'a, str>,
+        title: Option<Cow<'a, str>>,
+        inlines: Vec<Inline<'a>>,
     },
     Image {
-        _url: String,
-        _title: Option<String>,
-        inlines: Vec<Inline>,
+        _url: Cow<'a, str>,
+        _title: Option<Cow<'a, str>>,
+        inlines: Vec<Inline<'a>>,
     },
     CodeBlock {
-        text: String,
+        lang: Option<Cow<'a, str>>,
+        text: Cow<'a, str>,
     },
     HtmlBlock {
-        text: String,
+        text: Cow<'a, str>,
+    },
+    /// An open inline HTML element from the supported subset; `open` is the
+    /// tag as written, put back as text if the element is never closed.
+    HtmlInline {
+        tag: HtmlTag,
+        open: Cow<'a, str>,
+        inlines: Vec<Inline<'a>>,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Inline HTML elements with a Markdown equivalent. Anything else stays
+/// literal text.
+enum HtmlTag {
+    Bold,
+    Italic,
+    Code,
+    Keyboard,
+    Variable,
+    Superscript,
+}
+
+impl HtmlTag {
+    fn from_name(name: &str) -> Option<Self> {
+        let tag = match name.to_ascii_lowercase().as_str() {
+            "b" => HtmlTag::Bold,
+            "i" => HtmlTag::Italic,
+            "code" => HtmlTag::Code,
+            "kbd" => HtmlTag::Keyboard,
+            "var" => HtmlTag::Variable,
+            "sup" => HtmlTag::Superscript,
+            _ => return None,
+        };
+        Some(tag)
+    }
+
+    /// Keys are bold and placeholders italic, as man-pages(7) would have them.
+    fn wrap<'a>(self, inlines: Vec<Inline<'a>>) -> Inline<'a> {
+        match self {
+            HtmlTag::Bold | HtmlTag::Keyboard => Inline::Strong(inlines),
+            HtmlTag::Italic | HtmlTag::Variable => Inline::Emphasis(inlines),
+            HtmlTag::Code => Inline::Code(Cow::Owned(inline_text(&inlines))),
+            HtmlTag::Superscript => Inline::Superscript(inlines),
+        }
+    }
+}
+
+/// The pulldown-cmark extensions to parse with. `^`, `~` and `$` are
+/// everyday characters in paths, regexes and shell variables, so scripts and
+/// math are only parsed on request.
+fn markdown_options(scripts: bool, math: bool) -> Options {
+    let mut extensions =
+        Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH;
+    if scripts {
+        extensions |= Options::ENABLE_SUPERSCRIPT | Options::ENABLE_SUBSCRIPT;
+    }
+    if math {
+        extensions |= Options::ENABLE_MATH;
+    }
+    extensions
+}
+
 /// Parse CommonMark into a `Document` AST.
 ///
-/// Unsupported constructs (tables, footnotes, etc.) return a `Markdown` error
+/// Footnotes are parsed, and rendered into a `NOTES` section. Unsupported
+/// constructs (tables, metadata blocks, etc.) return a `Markdown` error
 /// instead of politely pretending they never existed.
-pub fn parse_markdown(markdown: &str) -> Result<Document> {
-    let parser = Parser::new_ext(markdown, Options::empty());
-    parse_events(parser)
+pub fn parse_markdown(markdown: &str) -> Result<Document<'_>> {
+    parse_with(markdown, markdown_options(false, false))
+}
+
+/// Parse CommonMark into a `Document` AST, with the syntax extensions that
+/// `options` turn on ([`ManpageOptions::scripts`] and [`ManpageOptions::math`]).
+pub fn parse_markdown_with_options<'a>(
+    markdown: &'a str,
+    options: &ManpageOptions,
+) -> Result<Document<'a>> {
+    parse_with(
+        markdown,
+        markdown_options(options.scripts, options.math.is_some()),
+    )
+}
+
+fn parse_with(markdown: &str, extensions: Options) -> Result<Document<'_>> {
+    let events = Parser::new_ext(markdown, extensions).into_offset_iter();
+    if extensions.contains(Options::ENABLE_SUBSCRIPT) {
+        return parse_events(events.map(|(event, _)| event), extensions);
+    }
+    parse_events(double_tilde_strikethrough(markdown, events), extensions)
 }
 
-fn parse_events<'a, I>(events: I) -> Result<Document>
+/// Without subscripts, pulldown-cmark reads `~one~` as strikethrough too,
+/// which would strike through `~/a~` paths. Only `~~two~~` strikes; the
+/// single tildes go back to being text.
+fn double_tilde_strikethrough<'a>(
+    markdown: &'a str,
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+) -> impl Iterator<Item = Event<'a>> {
+    let mut single = Vec::new();
+    events.map(move |(event, range)| match event {
+        Event::Start(Tag::Strikethrough) => {
+            let is_single = !markdown[range].starts_with("~~");
+            single.push(is_single);
+            if is_single {
+                Event::Text(CowStr::Borrowed("~"))
+            } else {
+                event
+            }
+        }
+        Event::End(TagEnd::Strikethrough) => {
+            if single.pop().unwrap_or(false) {
+                Event::Text(CowStr::Borrowed("~"))
+            } else {
+                event
+            }
+        }
+        other => other,
+    })
+}
+
+/// `extensions` is passed along for the Markdown nested in HTML blocks.
+fn parse_events<'a, I>(events: I, extensions: Options) -> Result<Document<'a>>
 where
     I: IntoIterator<Item = Event<'a>>,
 {
@@ -248,24 +1110,26 @@ where
     for event in events {
         match event {
             Event::Start(tag) => handle_start(tag, &mut stack)?,
-            Event::End(tag_end) => handle_end(tag_end, &mut stack)?,
+            Event::End(tag_end) => handle_end(tag_end, &mut stack, extensions)?,
             Event::Text(text) => {
-                if let Some(Frame::CodeBlock { text: buffer }) = stack.last_mut() {
-                    buffer.push_str(&text);
+                if let Some(Frame::CodeBlock { text: buffer, .. }) = stack.last_mut() {
+                    append_cow(buffer, text);
                 } else if let Some(Frame::HtmlBlock { text: buffer }) = stack.last_mut() {
-                    buffer.push_str(&text);
+                    append_cow(buffer, text);
                 } else {
-                    push_inline(&mut stack, Inline::Text(text.into_string()))?;
+                    push_inline(&mut stack, Inline::Text(cow_str(text)))?;
                 }
             }
             Event::Code(text) => {
-                push_inline(&mut stack, Inline::Code(text.into_string()))?;
+                push_inline(&mut stack, Inline::Code(cow_str(text)))?;
             }
             Event::Html(html) | Event::InlineHtml(html) => {
                 if let Some(Frame::HtmlBlock { text: buffer }) = stack.last_mut() {
-                    buffer.push_str(&html);
+                    append_cow(buffer, html);
+                } else if let Some(comment) = html_comment(&html) {
+                    push_inline(&mut stack, Inline::Comment(Cow::Owned(comment.to_string())))?;
                 } else {
-                    push_inline(&mut stack, Inline::Text(html.into_string()))?;
+                    push_inline_html(&mut stack, cow_str(html))?;
                 }
             }
             Event::SoftBreak => {
@@ -275,23 +1139,32 @@ where
                 push_inline(&mut stack, Inline::LineBreak(LineBreak::Hard))?;
             }
             Event::FootnoteReference(label) => {
-                push_inline(&mut stack, Inline::Text(label.into_string()))?;
+                push_inline(&mut stack, Inline::FootnoteReference(cow_str(label)))?;
             }
             Event::Rule => {
-                // Ignore horizontal rules for now.
+                flush_pending_block_container(&mut stack);
+                push_block(&mut stack, Block::Rule)?;
             }
-            Event::TaskListMarker(_) => {
-                // Ignore task list markers for now.
+            Event::TaskListMarker(done) => {
+                // Arrives inside the item's first paragraph when the list is loose.
+                let item = stack.iter_mut().rev().find_map(|frame| match frame {
+                    Frame::ListItem { checked, .. } => Some(checked),
+                    _ => None,
+                });
+                if let Some(checked) = item {
+                    *checked = Some(done);
+                }
             }
-            Event::InlineMath(_) | Event::DisplayMath(_) => {
-                // Ignore math for now.
+            Event::InlineMath(tex) => {
+                push_inline(&mut stack, Inline::Math(cow_str(tex)))?;
             }
+            Event::DisplayMath(tex) => push_display_math(&mut stack, cow_str(tex))?,
         }
     }
     finish_stack(stack)
 }
 
-fn finish_stack(mut stack: Vec<Frame>) -> Result<Document> {
+fn finish_stack(mut stack: Vec<Frame<'_>>) -> Result<Document<'_>> {
     if stack.len() != 1 {
         return Err(MandateError::Markdown(
             "unbalanced markdown structure".to_string(),
@@ -308,7 +1181,7 @@ fn finish_stack(mut stack: Vec<Frame>) -> Result<Document> {
     }
 }
 
-fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
+fn handle_start<'a>(tag: Tag<'a>, stack: &mut Vec<Frame<'a>>) -> Result<()> {
     match tag {
         Tag::Paragraph => {
             flush_pending_block_container(stack);
@@ -334,12 +1207,20 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
             });
         }
         Tag::Item => {
-            stack.push(Frame::ListItem(BlockContainerFrame::default()));
+            stack.push(Frame::ListItem {
+                frame: BlockContainerFrame::default(),
+                checked: None,
+            });
         }
-        Tag::CodeBlock(_) => {
+        Tag::CodeBlock(kind) => {
             flush_pending_block_container(stack);
+            let lang = match kind {
+                CodeBlockKind::Fenced(info) => fence_lang(info),
+                CodeBlockKind::Indented => None,
+            };
             stack.push(Frame::CodeBlock {
-                text: String::new(),
+                lang,
+                text: Cow::Borrowed(""),
             });
         }
         Tag::BlockQuote(_) => {
@@ -349,7 +1230,7 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
         Tag::HtmlBlock => {
             flush_pending_block_container(stack);
             stack.push(Frame::HtmlBlock {
-                text: String::new(),
+                text: Cow::Borrowed(""),
             });
         }
         Tag::Emphasis => {
@@ -362,16 +1243,31 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
                 inlines: Vec::new(),
             });
         }
+        Tag::Strikethrough => {
+            stack.push(Frame::Strikethrough {
+                inlines: Vec::new(),
+            });
+        }
+        Tag::Superscript => {
+            stack.push(Frame::Superscript {
+                inlines: Vec::new(),
+            });
+        }
+        Tag::Subscript => {
+            stack.push(Frame::Subscript {
+                inlines: Vec::new(),
+            });
+        }
         Tag::Link {
             dest_url, title, ..
         } => {
             let title = if title.is_empty() {
                 None
             } else {
-                Some(title.into_string())
+                Some(cow_str(title))
             };
             stack.push(Frame::Link {
-                url: dest_url.into_string(),
+                url: cow_str(dest_url),
                 title,
                 inlines: Vec::new(),
             });
@@ -382,10 +1278,10 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
             let title = if title.is_empty() {
                 None
             } else {
-                Some(title.into_string())
+                Some(cow_str(title))
             };
             stack.push(Frame::Image {
-                _url: dest_url.into_string(),
+                _url: cow_str(dest_url),
                 _title: title,
                 inlines: Vec::new(),
             });
@@ -395,14 +1291,17 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
                 "tables are not supported".to_string(),
             ));
         }
-        Tag::FootnoteDefinition(_)
-        | Tag::Strikethrough
-        | Tag::MetadataBlock(_)
+        Tag::FootnoteDefinition(label) => {
+            flush_pending_block_container(stack);
+            stack.push(Frame::FootnoteDefinition {
+                label: cow_str(label),
+                frame: BlockContainerFrame::default(),
+            });
+        }
+        Tag::MetadataBlock(_)
         | Tag::DefinitionList
         | Tag::DefinitionListTitle
-        | Tag::DefinitionListDefinition
-        | Tag::Superscript
-        | Tag::Subscript => {
+        | Tag::DefinitionListDefinition => {
             return Err(MandateError::Markdown(
                 "unsupported markdown construct encountered".to_string(),
             ));
@@ -411,14 +1310,15 @@ fn handle_start(tag: Tag<'_>, stack: &mut Vec<Frame>) -> Result<()> {
     Ok(())
 }
 
-fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
+fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame<'_>>, extensions: Options) -> Result<()> {
+    unwind_inline_html(stack)?;
     match tag_end {
         TagEnd::Paragraph => {
             let inlines = match stack.pop() {
                 Some(Frame::Paragraph { inlines }) => inlines,
                 _ => return Err(MandateError::Markdown("paragraph mismatch".to_string())),
             };
-            push_block(stack, Block::Paragraph(inlines))?;
+            push_paragraph(stack, inlines)?;
         }
         TagEnd::Heading(_) => {
             let (level, inlines) = match stack.pop() {
@@ -441,11 +1341,13 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
             push_block(stack, Block::List { kind, items })?;
         }
         TagEnd::Item => {
-            let blocks = match stack.pop() {
-                Some(Frame::ListItem(frame)) => frame.finish(),
+            let list_item = match stack.pop() {
+                Some(Frame::ListItem { frame, checked }) => ListItem {
+                    blocks: frame.finish(),
+                    checked,
+                },
                 _ => return Err(MandateError::Markdown("list item mismatch".to_string())),
             };
-            let list_item = ListItem { blocks };
             match stack.last_mut() {
                 Some(Frame::List { items, .. }) => items.push(list_item),
                 _ => {
@@ -455,6 +1357,13 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
                 }
             }
         }
+        TagEnd::FootnoteDefinition => {
+            let (label, blocks) = match stack.pop() {
+                Some(Frame::FootnoteDefinition { label, frame }) => (label, frame.finish()),
+                _ => return Err(MandateError::Markdown("footnote mismatch".to_string())),
+            };
+            push_block(stack, Block::FootnoteDefinition { label, blocks })?;
+        }
         TagEnd::Emphasis => {
             let inlines = match stack.pop() {
                 Some(Frame::Emphasis { inlines }) => inlines,
@@ -469,6 +1378,27 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
             };
             push_inline(stack, Inline::Strong(inlines))?;
         }
+        TagEnd::Strikethrough => {
+            let inlines = match stack.pop() {
+                Some(Frame::Strikethrough { inlines }) => inlines,
+                _ => return Err(MandateError::Markdown("strikethrough mismatch".to_string())),
+            };
+            push_inline(stack, Inline::Strikethrough(inlines))?;
+        }
+        TagEnd::Superscript => {
+            let inlines = match stack.pop() {
+                Some(Frame::Superscript { inlines }) => inlines,
+                _ => return Err(MandateError::Markdown("superscript mismatch".to_string())),
+            };
+            push_inline(stack, Inline::Superscript(inlines))?;
+        }
+        TagEnd::Subscript => {
+            let inlines = match stack.pop() {
+                Some(Frame::Subscript { inlines }) => inlines,
+                _ => return Err(MandateError::Markdown("subscript mismatch".to_string())),
+            };
+            push_inline(stack, Inline::Subscript(inlines))?;
+        }
         TagEnd::Link => {
             let (url, title, inlines) = match stack.pop() {
                 Some(Frame::Link {
@@ -493,21 +1423,32 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
                 _ => return Err(MandateError::Markdown("image mismatch".to_string())),
             };
             let text = inline_text(&inlines);
-            push_inline(stack, Inline::Text(text))?;
+            push_inline(stack, Inline::Text(Cow::Owned(text)))?;
         }
         TagEnd::CodeBlock => {
-            let text = match stack.pop() {
-                Some(Frame::CodeBlock { text }) => text,
+            let (lang, text) = match stack.pop() {
+                Some(Frame::CodeBlock { lang, text }) => (lang, text),
                 _ => return Err(MandateError::Markdown("code block mismatch".to_string())),
             };
-            push_block(stack, Block::CodeBlock { text })?;
+            let block = match lang {
+                Some(format) if format == "roff" => Block::Raw { format, text },
+                lang => Block::CodeBlock { lang, text },
+            };
+            push_block(stack, block)?;
         }
         TagEnd::HtmlBlock => {
             let text = match stack.pop() {
                 Some(Frame::HtmlBlock { text }) => text,
                 _ => return Err(MandateError::Markdown("html block mismatch".to_string())),
             };
-            push_block(stack, Block::Paragraph(vec![Inline::Text(text)]))?;
+            let block = match html_comment(&text) {
+                Some(comment) => comment_block(comment),
+                None => match definition_list(&text, extensions)? {
+                    Some(items) => Block::DefinitionList { items },
+                    None => Block::Paragraph(vec![Inline::Text(text)]),
+                },
+            };
+            push_block(stack, block)?;
         }
         TagEnd::BlockQuote(_) => {
             let blocks = match stack.pop() {
@@ -526,14 +1467,10 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
         | TagEnd::TableHead
         | TagEnd::TableRow
         | TagEnd::TableCell
-        | TagEnd::FootnoteDefinition
-        | TagEnd::Strikethrough
         | TagEnd::MetadataBlock(_)
         | TagEnd::DefinitionList
         | TagEnd::DefinitionListTitle
-        | TagEnd::DefinitionListDefinition
-        | TagEnd::Superscript
-        | TagEnd::Subscript => {
+        | TagEnd::DefinitionListDefinition => {
             return Err(MandateError::Markdown(
                 "unsupported markdown construct encountered".to_string(),
             ));
@@ -542,17 +1479,57 @@ fn handle_end(tag_end: TagEnd, stack: &mut Vec<Frame>) -> Result<()> {
     Ok(())
 }
 
-fn push_inline(stack: &mut [Frame], inline: Inline) -> Result<()> {
+/// Display math is an inline event to pulldown-cmark, but a block to us: it
+/// splits the paragraph it appears in. Anywhere else (emphasis, headings)
+/// it stays inline.
+fn push_display_math<'a>(stack: &mut Vec<Frame<'a>>, tex: Cow<'a, str>) -> Result<()> {
+    if !matches!(stack.last(), Some(Frame::Paragraph { .. })) {
+        return push_inline(stack, Inline::Math(tex));
+    }
+    if let Some(Frame::Paragraph { inlines }) = stack.pop() {
+        push_paragraph(stack, inlines)?;
+    }
+    push_block(stack, Block::Math { tex })?;
+    stack.push(Frame::Paragraph {
+        inlines: Vec::new(),
+    });
+    Ok(())
+}
+
+/// Push a paragraph, minus the line breaks left dangling at either end when
+/// display math was cut out of it. Nothing left means nothing pushed.
+fn push_paragraph<'a>(stack: &mut [Frame<'a>], mut inlines: Vec<Inline<'a>>) -> Result<()> {
+    tidy_html_spacing(&mut inlines);
+    while matches!(inlines.last(), Some(Inline::LineBreak(_))) {
+        inlines.pop();
+    }
+    let leading = inlines
+        .iter()
+        .take_while(|inline| matches!(inline, Inline::LineBreak(_)))
+        .count();
+    inlines.drain(..leading);
+    if inlines.is_empty() {
+        return Ok(());
+    }
+    push_block(stack, Block::Paragraph(inlines))
+}
+
+fn push_inline<'a>(stack: &mut [Frame<'a>], inline: Inline<'a>) -> Result<()> {
     match stack.last_mut() {
         Some(Frame::Paragraph { inlines })
         | Some(Frame::Heading { inlines, .. })
         | Some(Frame::Emphasis { inlines })
         | Some(Frame::Strong { inlines })
+        | Some(Frame::Strikethrough { inlines })
+        | Some(Frame::Superscript { inlines })
+        | Some(Frame::Subscript { inlines })
         | Some(Frame::Link { inlines, .. })
-        | Some(Frame::Image { inlines, .. }) => {
+        | Some(Frame::Image { inlines, .. })
+        | Some(Frame::HtmlInline { inlines, .. }) => {
             inlines.push(inline);
         }
-        Some(Frame::ListItem(frame))
+        Some(Frame::ListItem { frame, .. })
+        | Some(Frame::FootnoteDefinition { frame, .. })
         | Some(Frame::Document(frame))
         | Some(Frame::BlockContainer(frame)) => {
             frame.push_inline(inline);
@@ -576,10 +1553,11 @@ fn push_inline(stack: &mut [Frame], inline: Inline) -> Result<()> {
     Ok(())
 }
 
-fn push_block(stack: &mut [Frame], block: Block) -> Result<()> {
+fn push_block<'a>(stack: &mut [Frame<'a>], block: Block<'a>) -> Result<()> {
     match stack.last_mut() {
         Some(Frame::Document(frame))
-        | Some(Frame::ListItem(frame))
+        | Some(Frame::ListItem { frame, .. })
+        | Some(Frame::FootnoteDefinition { frame, .. })
         | Some(Frame::BlockContainer(frame)) => {
             frame.push_block(block);
             Ok(())
@@ -593,15 +1571,259 @@ fn push_block(stack: &mut [Frame], block: Block) -> Result<()> {
     }
 }
 
-fn flush_pending_block_container(stack: &mut [Frame]) {
+fn flush_pending_block_container(stack: &mut [Frame<'_>]) {
     if let Some(Frame::Document(frame))
-    | Some(Frame::ListItem(frame))
+    | Some(Frame::ListItem { frame, .. })
+    | Some(Frame::FootnoteDefinition { frame, .. })
     | Some(Frame::BlockContainer(frame)) = stack.last_mut()
     {
         frame.flush_pending();
     }
 }
 
+/// Handle an inline HTML tag: open or close an element from the supported
+/// subset, break the line for `<br>`, and keep anything else as text.
+fn push_inline_html<'a>(stack: &mut Vec<Frame<'a>>, html: Cow<'a, str>) -> Result<()> {
+    let Some((closing, name)) = html_tag(&html) else {
+        return push_inline(stack, Inline::Text(html));
+    };
+    if name.eq_ignore_ascii_case("br") {
+        return push_inline(stack, Inline::LineBreak(LineBreak::Hard));
+    }
+    match HtmlTag::from_name(name) {
+        Some(tag) if !closing => {
+            stack.push(Frame::HtmlInline {
+                tag,
+                open: html,
+                inlines: Vec::new(),
+            });
+            Ok(())
+        }
+        Some(tag) if matches!(stack.last(), Some(Frame::HtmlInline { tag: open, .. }) if *open == tag) =>
+        {
+            let inlines = match stack.pop() {
+                Some(Frame::HtmlInline { inlines, .. }) => inlines,
+                _ => return Err(MandateError::Markdown("inline html mismatch".to_string())),
+            };
+            push_inline(stack, tag.wrap(inlines))
+        }
+        _ => push_inline(stack, Inline::Text(html)),
+    }
+}
+
+/// Put inline HTML elements still open at the end of their container back
+/// as text: `<b>` never closed is just `<b>`.
+fn unwind_inline_html(stack: &mut Vec<Frame<'_>>) -> Result<()> {
+    while let Some(Frame::HtmlInline { .. }) = stack.last() {
+        let (open, inlines) = match stack.pop() {
+            Some(Frame::HtmlInline { open, inlines, .. }) => (open, inlines),
+            _ => return Err(MandateError::Markdown("inline html mismatch".to_string())),
+        };
+        push_inline(stack, Inline::Text(open))?;
+        for inline in inlines {
+            push_inline(stack, inline)?;
+        }
+    }
+    Ok(())
+}
+
+/// Split a lone HTML tag into whether it closes and its name: `<kbd>`,
+/// `</kbd>`, `<br/>` and `<var class="x">` all qualify.
+fn html_tag(html: &str) -> Option<(bool, &str)> {
+    let inner = html.trim().strip_prefix('<')?.strip_suffix('>')?;
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, inner),
+    };
+    let inner = inner.strip_suffix('/').unwrap_or(inner);
+    let name = inner
+        .split(|ch: char| ch.is_whitespace())
+        .next()
+        .filter(|name| !name.is_empty() && name.chars().all(|ch| ch.is_ascii_alphanumeric()))?;
+    Some((closing, name))
+}
+
+/// Read an HTML block that is exactly one `<dl>` of `<dt>` terms and `<dd>`
+/// definitions. Their contents are parsed as Markdown, so the inline subset
+/// works there too. Anything else (including a nested `<dl>`) is `None`.
+fn definition_list(html: &str, extensions: Options) -> Result<Option<Vec<Definition<'static>>>> {
+    let html = html.trim();
+    let mut pos = match html_tag_at(html) {
+        Some((false, "dl", len)) => len,
+        _ => return Ok(None),
+    };
+    let mut items: Vec<Definition<'static>> = Vec::new();
+    // The `dt` or `dd` being read, and where its content starts.
+    let mut open: Option<(&str, usize)> = None;
+    loop {
+        let Some(start) = html[pos..].find('<').map(|offset| pos + offset) else {
+            return Ok(None);
+        };
+        let Some((closing, name, len)) =
+            html_tag_at(&html[start..]).filter(|tag| !tag.1.is_empty())
+        else {
+            pos = start + 1;
+            continue;
+        };
+        if let Some((element, from)) = open.take() {
+            let blocks = parse_with(html[from..start].trim(), extensions)?
+                .into_owned()
+                .blocks;
+            if element == "dt" {
+                let term = match blocks.into_iter().next() {
+                    Some(Block::Paragraph(inlines)) => inlines,
+                    _ => Vec::new(),
+                };
+                items.push(Definition {
+                    term,
+                    blocks: Vec::new(),
+                });
+            } else if let Some(item) = items.last_mut() {
+                item.blocks.extend(blocks);
+            } else {
+                return Ok(None);
+            }
+        }
+        pos = start + len;
+        match (closing, name) {
+            (true, "dl") => break,
+            (false, "dl") => return Ok(None),
+            (false, element) => open = Some((element, pos)),
+            (true, _) => {}
+        }
+    }
+    Ok((html[pos..].trim().is_empty() && !items.is_empty()).then_some(items))
+}
+
+/// The tag at the start of `html`, if any, with its length in bytes. Names
+/// are lowercased to `dl`/`dt`/`dd` so callers can match on them.
+fn html_tag_at(html: &str) -> Option<(bool, &'static str, usize)> {
+    let len = html.find('>')? + 1;
+    let (closing, name) = html_tag(&html[..len])?;
+    let name = ["dl", "dt", "dd"]
+        .into_iter()
+        .find(|known| name.eq_ignore_ascii_case(known))
+        .unwrap_or("");
+    Some((closing, name, len))
+}
+
+/// The inside of `html` if it is exactly one comment, trimmed.
+fn html_comment(html: &str) -> Option<&str> {
+    let body = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    (!body.contains("-->")).then(|| body.trim())
+}
+
+/// Sort a standalone comment into roff passthrough (`roff: ...`, copied as
+/// written after the marker), a directive (`mandate: name args`) or a plain
+/// comment.
+fn comment_block(comment: &str) -> Block<'static> {
+    if let Some(roff) = comment.strip_prefix("roff:") {
+        // Leading whitespace and blank lines mean something in roff (`.nf`
+        // bodies, tbl data), so only the rest of the marker line is trimmed.
+        let text = match roff.split_once('\n') {
+            Some((first, rest)) if first.trim().is_empty() => rest.to_string(),
+            Some((first, rest)) => format!("{}\n{rest}", first.trim()),
+            None => roff.trim().to_string(),
+        };
+        Block::Raw {
+            format: Cow::Borrowed("roff"),
+            text: Cow::Owned(format!("{text}\n")),
+        }
+    } else if let Some(directive) = comment.strip_prefix("mandate:") {
+        let directive = directive.trim();
+        let (name, args) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        Block::Directive {
+            name: Cow::Owned(name.to_string()),
+            args: Cow::Owned(args.trim().to_string()),
+        }
+    } else {
+        Block::Comment(Cow::Owned(comment.to_string()))
+    }
+}
+
+/// Close the gaps HTML leaves in a paragraph: a dropped comment in
+/// `a <!-- c --> b` reads `a b`, not `a  b`, a comment ending a line takes
+/// its space along, and a `<br>` at the end of a line swallows the soft
+/// break that follows it.
+fn tidy_html_spacing(inlines: &mut Vec<Inline<'_>>) {
+    let mut idx = 1;
+    while idx < inlines.len() {
+        if matches!(
+            (&inlines[idx - 1], &inlines[idx]),
+            (
+                Inline::LineBreak(LineBreak::Hard),
+                Inline::LineBreak(LineBreak::Soft)
+            )
+        ) {
+            inlines.remove(idx);
+        } else {
+            idx += 1;
+        }
+    }
+    for idx in 0..inlines.len() {
+        if !matches!(inlines[idx], Inline::Comment(_)) {
+            continue;
+        }
+        let before = inlines[..idx]
+            .iter()
+            .rposition(|inline| !matches!(inline, Inline::Comment(_)));
+        let after = inlines[idx + 1..]
+            .iter()
+            .position(|inline| !matches!(inline, Inline::Comment(_)))
+            .map(|offset| idx + 1 + offset);
+        let spaced_before = before.is_none_or(|before| {
+            matches!(&inlines[before], Inline::Text(text) if text.ends_with(char::is_whitespace))
+        });
+        match after.map(|after| &mut inlines[after]) {
+            Some(Inline::Text(text)) if spaced_before => trim_cow(text, str::trim_start),
+            None | Some(Inline::LineBreak(_)) => {
+                if let Some(Inline::Text(text)) = before.map(|before| &mut inlines[before]) {
+                    trim_cow(text, str::trim_end);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn trim_cow(text: &mut Cow<'_, str>, trim: fn(&str) -> &str) {
+    match text {
+        Cow::Borrowed(borrowed) => *borrowed = trim(borrowed),
+        Cow::Owned(owned) => *owned = trim(owned).to_string(),
+    }
+}
+
+/// The language of a fence: the first word of its info string, so
+/// ```` ```sh title="x" ```` is just `sh`.
+fn fence_lang(info: CowStr<'_>) -> Option<Cow<'_, str>> {
+    match cow_str(info) {
+        Cow::Borrowed(info) => info.split_whitespace().next().map(Cow::Borrowed),
+        Cow::Owned(info) => info
+            .split_whitespace()
+            .next()
+            .map(|lang| Cow::Owned(lang.to_string())),
+    }
+}
+
+fn cow_str(value: CowStr<'_>) -> Cow<'_, str> {
+    match value {
+        CowStr::Borrowed(text) => Cow::Borrowed(text),
+        other => Cow::Owned(other.into_string()),
+    }
+}
+
+/// Append parser text to a buffer, staying borrowed while there is only one
+/// chunk.
+fn append_cow<'a>(buffer: &mut Cow<'a, str>, text: CowStr<'a>) {
+    if buffer.is_empty() {
+        *buffer = cow_str(text);
+    } else {
+        buffer.to_mut().push_str(&text);
+    }
+}
+
 fn heading_level_to_u8(level: HeadingLevel) -> u8 {
     match level {
         HeadingLevel::H1 => 1,
@@ -613,50 +1835,278 @@ fn heading_level_to_u8(level: HeadingLevel) -> u8 {
     }
 }
 
-fn inline_text(inlines: &[Inline]) -> String {
+fn inline_text(inlines: &[Inline<'_>]) -> String {
     let mut out = String::new();
     for inline in inlines {
         match inline {
             Inline::Text(text) | Inline::Code(text) => out.push_str(text),
             Inline::Emphasis(children)
             | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Superscript(children)
+            | Inline::Subscript(children)
             | Inline::Link {
                 content: children, ..
             } => out.push_str(&inline_text(children)),
             Inline::LineBreak(LineBreak::Soft) | Inline::LineBreak(LineBreak::Hard) => {
                 out.push('\n');
             }
+            Inline::FootnoteReference(_) | Inline::Comment(_) => {}
+            Inline::Math(tex) => out.push_str(tex),
         }
     }
     out
 }
 
-struct RoffWriter {
-    output: String,
-}
+/// Callbacks for turning a [`Document`] into some output format.
+///
+/// [`render`] drives a renderer over a document: it calls
+/// [`Renderer::start`], hands the top-level blocks to [`Renderer::blocks`],
+/// and finishes with [`Renderer::finish`]. The default methods walk the tree
+/// down to [`Renderer::inline`], so a minimal renderer only has to implement
+/// `heading`, `code_block` and `inline`. Override `blocks` when a block's
+/// output depends on its neighbours (the roff renderer does).
+///
+/// ```
+/// use mandate::{Inline, Renderer, Result};
+///
+/// #[derive(Default)]
+/// struct Outline(Vec<String>);
+///
+/// impl Renderer for Outline {
+///     fn heading(&mut self, level: u8, content: &[Inline<'_>]) -> Result<()> {
+///         let text: String = content
+///             .iter()
+///             .filter_map(|inline| match inline {
+///                 Inline::Text(text) => Some(text.as_ref()),
+///                 _ => None,
+///             })
+///             .collect();
+///         self.0.push(format!("{} {text}", "#".repeat(level.into())));
+///         Ok(())
+///     }
+///
+///     fn code_block(&mut self, _lang: Option<&str>, _text: &str) -> Result<()> {
+///         Ok(())
+///     }
+///
+///     fn inline(&mut self, _inline: &Inline<'_>) -> Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let document = mandate::parse_markdown("# tool(1) -- Does things\n\n## SYNOPSIS\n")?;
+/// let options = mandate::ManpageOptions::new("tool", "1", "Tool Manual", None, None);
+/// let mut outline = Outline::default();
+/// mandate::render(&document, &options, &mut outline)?;
+/// assert_eq!(outline.0, ["# tool(1) -- Does things", "## SYNOPSIS"]);
+/// # Ok::<(), mandate::MandateError>(())
+/// ```
+pub trait Renderer {
+    /// Called once before any block, with the manpage header options.
+    fn start(&mut self, _options: &ManpageOptions) -> Result<()> {
+        Ok(())
+    }
 
-impl RoffWriter {
-    fn new() -> Self {
-        Self {
-            output: String::new(),
+    /// Called once after every block has been rendered.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Render a run of sibling blocks (the document body or a list item).
+    fn blocks(&mut self, blocks: &[Block<'_>]) -> Result<()> {
+        for block in blocks {
+            self.block(block)?;
         }
+        Ok(())
     }
 
-    fn finish(self) -> String {
-        self.output
-            .lines()
-            .map(|line| {
-                if line.starts_with("\\.") {
-                    format!("\\&{}", line)
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
+    /// Render a single block by dispatching to the matching method.
+    fn block(&mut self, block: &Block<'_>) -> Result<()> {
+        match block {
+            Block::Heading { level, content } => self.heading(*level, content),
+            Block::Paragraph(content) => self.paragraph(content),
+            Block::List { kind, items } => self.list(kind, items),
+            Block::CodeBlock { lang, text } => self.code_block(lang.as_deref(), text),
+            Block::Rule => self.rule(),
+            Block::FootnoteDefinition { label, blocks } => self.footnote_definition(label, blocks),
+            Block::Math { tex } => self.math(tex),
+            Block::Raw { format, text } => self.raw(format, text),
+            Block::DefinitionList { items } => self.definition_list(items),
+            Block::Comment(text) => self.comment(text),
+            Block::Directive { name, args } => self.directive(name, args),
+        }
     }
 
-    fn write_header(&mut self, options: &ManpageOptions) {
+    /// Render a heading.
+    fn heading(&mut self, level: u8, content: &[Inline<'_>]) -> Result<()>;
+
+    /// Render a paragraph. Defaults to rendering its inlines.
+    fn paragraph(&mut self, content: &[Inline<'_>]) -> Result<()> {
+        self.inlines(content)
+    }
+
+    /// Render a list. Defaults to rendering each item in turn.
+    fn list(&mut self, _kind: &ListKind, items: &[ListItem<'_>]) -> Result<()> {
+        for item in items {
+            self.list_item(item)?;
+        }
+        Ok(())
+    }
+
+    /// Render a list item. Defaults to rendering its blocks.
+    fn list_item(&mut self, item: &ListItem<'_>) -> Result<()> {
+        self.blocks(&item.blocks)
+    }
+
+    /// Render a code block's text, with the fence language if there was one.
+    fn code_block(&mut self, lang: Option<&str>, text: &str) -> Result<()>;
+
+    /// Render a horizontal rule. Defaults to nothing, which is what most
+    /// outline-style renderers want.
+    fn rule(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Render display math, given as TeX. Defaults to showing the TeX as a
+    /// code block, which beats losing it.
+    fn math(&mut self, tex: &str) -> Result<()> {
+        self.code_block(Some("tex"), tex)
+    }
+
+    /// Render output meant for the backend named `format`. Defaults to
+    /// dropping it; a renderer that would rather fail loudly can return an
+    /// error instead.
+    fn raw(&mut self, _format: &str, _text: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Render a definition list. Defaults to each term as a paragraph,
+    /// followed by its definition.
+    fn definition_list(&mut self, items: &[Definition<'_>]) -> Result<()> {
+        for item in items {
+            self.paragraph(&item.term)?;
+            self.blocks(&item.blocks)?;
+        }
+        Ok(())
+    }
+
+    /// Render a standalone comment. Defaults to dropping it.
+    fn comment(&mut self, _text: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Act on a `<!-- mandate: name args -->` directive. Nothing is defined
+    /// yet, so the default ignores them all.
+    fn directive(&mut self, _name: &str, _args: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Render a footnote body where it was defined. Defaults to rendering
+    /// its blocks in place; the roff writer moves them to `NOTES` instead.
+    fn footnote_definition(&mut self, _label: &str, blocks: &[Block<'_>]) -> Result<()> {
+        self.blocks(blocks)
+    }
+
+    /// Render a run of inlines. Defaults to rendering each one in turn.
+    fn inlines(&mut self, inlines: &[Inline<'_>]) -> Result<()> {
+        for inline in inlines {
+            self.inline(inline)?;
+        }
+        Ok(())
+    }
+
+    /// Render a single inline node.
+    fn inline(&mut self, inline: &Inline<'_>) -> Result<()>;
+}
+
+/// Drive `renderer` over `document`.
+pub fn render<R: Renderer + ?Sized>(
+    document: &Document<'_>,
+    options: &ManpageOptions,
+    renderer: &mut R,
+) -> Result<()> {
+    renderer.start(options)?;
+    renderer.blocks(&document.blocks)?;
+    renderer.finish()
+}
+
+struct RoffWriter<W: Write> {
+    out: W,
+    line_start: bool,
+    charset: Charset,
+    hyphens: HyphenPolicy,
+    sentence_per_line: bool,
+    sentence: Sentence,
+    strikethrough: StrikethroughStyle,
+    link_notes: bool,
+    math: MathMode,
+    tab_width: usize,
+    merge_code_blocks: bool,
+    comments: CommentMode,
+    /// Inline comments waiting for the end of the current output line.
+    pending_comments: Vec<String>,
+    /// Whether the `.EX`/`.EE` fallback definitions have been written.
+    examples_defined: bool,
+    /// Footnotes and link URLs, numbered by first reference.
+    notes: Vec<Note>,
+    /// Whether the current `.SH` is already called `NOTES`.
+    in_notes_section: bool,
+    /// Levels of the headings below `.SS` whose content is inside an open
+    /// `.RS`, outermost first.
+    heading_indents: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+/// An entry for the generated `NOTES` section.
+enum Note {
+    /// A footnote; `blocks` stays `None` until its definition turns up.
+    Footnote {
+        label: String,
+        blocks: Option<Vec<Block<'static>>>,
+    },
+    Link {
+        text: String,
+        url: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where sentence-per-line output stands after the last inline.
+enum Sentence {
+    /// Mid-sentence.
+    Open,
+    /// The last character ended a sentence.
+    Ended,
+    /// A sentence ended and was followed by whitespace, which we swallowed:
+    /// whatever comes next starts a new line.
+    Break,
+}
+
+impl<W: Write> RoffWriter<W> {
+    fn new(out: W, options: &ManpageOptions) -> Self {
+        Self {
+            out,
+            line_start: true,
+            charset: options.charset,
+            hyphens: options.hyphens,
+            sentence_per_line: options.sentence_per_line,
+            sentence: Sentence::Open,
+            strikethrough: options.strikethrough,
+            link_notes: options.link_notes,
+            math: options.math.unwrap_or_default(),
+            tab_width: options.tab_width,
+            merge_code_blocks: options.merge_code_blocks,
+            comments: options.comments,
+            pending_comments: Vec::new(),
+            examples_defined: false,
+            notes: Vec::new(),
+            in_notes_section: false,
+            heading_indents: Vec::new(),
+        }
+    }
+
+    fn write_header(&mut self, options: &ManpageOptions) -> Result<()> {
         let manual_value = options.manual_section.as_deref().unwrap_or(&options.title);
         let manual = format!("\"{}\"", self.sanitize(manual_value));
         let source = options
@@ -673,36 +2123,55 @@ impl RoffWriter {
             source,
             manual
         );
-        self.write_cmd(&heading);
+        if self.math == MathMode::Eqn {
+            self.write_cmd("'\\\" e")?;
+            self.write_cmd(&heading)?;
+            self.write_cmd(".EQ\ndelim $$\n.EN")
+        } else {
+            self.write_cmd(&heading)
+        }
     }
 
-    fn write_blocks(&mut self, blocks: &[Block], parent: Option<ParentTag>) {
+    fn write_blocks(&mut self, blocks: &[Block<'_>], parent: Option<ParentTag>) -> Result<()> {
         let mut idx = 0;
         let mut last_heading = false;
         while idx < blocks.len() {
             match &blocks[idx] {
                 Block::Heading { level, content } => {
-                    self.write_heading(*level, content);
-                    last_heading = matches!(level, 2 | 3);
+                    self.write_heading(*level, content)?;
+                    last_heading = *level >= 2;
                 }
                 Block::Paragraph(inlines) => {
-                    if !matches!(parent, Some(ParentTag::ListItem)) && !last_heading {
-                        self.write_cmd(".P");
+                    if !matches!(parent, Some(ParentTag::ListItem)) {
+                        if !last_heading {
+                            self.write_cmd(".P")?;
+                        }
+                    } else if idx > 0
+                        && matches!(
+                            blocks[idx - 1],
+                            Block::CodeBlock { .. } | Block::Math { .. }
+                        )
+                    {
+                        // `.RE` dropped us back to the margin; rejoin the item.
+                        self.write_cmd(".IP")?;
                     }
-                    self.write_inlines(inlines, false);
-                    self.write_raw("\n");
+                    self.write_inlines(inlines, false)?;
+                    self.write_raw("\n")?;
                     last_heading = false;
                 }
                 Block::List { kind, items } => {
-                    let consumed = self.write_list(kind, items, &blocks[idx + 1..]);
+                    let consumed = self.write_list(kind, items, &blocks[idx + 1..])?;
                     idx += consumed;
                     last_heading = false;
                 }
-                Block::CodeBlock { .. } => {
+                Block::CodeBlock { lang, .. } => {
                     let mut combined = String::new();
                     let mut first = true;
                     let mut offset = idx;
-                    while let Some(Block::CodeBlock { text }) = blocks.get(offset) {
+                    while let Some(Block::CodeBlock { lang: next, text }) = blocks.get(offset) {
+                        if next != lang || (!first && !self.merge_code_blocks) {
+                            break;
+                        }
                         if !first {
                             combined.push('\n');
                         }
@@ -710,128 +2179,564 @@ impl RoffWriter {
                         first = false;
                         offset += 1;
                     }
-                    self.write_cmd(".IP \"\" 4");
-                    self.write_cmd(".nf\n");
-                    self.write_raw(&self.pre_sanitize(&combined));
-                    if !combined.ends_with('\n') {
-                        self.write_raw("\n");
-                    }
-                    self.write_cmd(".fi");
-                    self.write_cmd(".IP \"\" 0");
+                    self.write_code_block(lang.as_deref(), &combined)?;
                     idx = offset - 1;
                     last_heading = false;
                 }
+                Block::Rule => {
+                    self.write_rule()?;
+                    last_heading = false;
+                }
+                Block::FootnoteDefinition { label, blocks } => {
+                    self.define_footnote(label, blocks);
+                }
+                Block::Math { tex } => {
+                    self.write_math_block(tex)?;
+                    last_heading = false;
+                }
+                Block::Raw { format, text } => {
+                    self.write_raw_block(format, text)?;
+                    last_heading = false;
+                }
+                Block::DefinitionList { items } => {
+                    self.write_definition_list(items)?;
+                    last_heading = false;
+                }
+                Block::Comment(text) => self.write_comment(text)?,
+                Block::Directive { .. } => {}
             }
             idx += 1;
         }
+        Ok(())
+    }
+
+    /// Code goes in an `.EX`/`.EE` example inside `.RS`/`.RE`, which nests
+    /// in list items and leaves the surrounding indentation alone.
+    fn write_code_block(&mut self, lang: Option<&str>, text: &str) -> Result<()> {
+        self.define_examples()?;
+        let text = expand_tabs(text, self.tab_width);
+        let styled = match lang {
+            Some("console" | "shell-session" | "shellsession") => self.style_console(&text),
+            Some("sh" | "bash" | "shell" | "zsh") => self.style_shell(&text),
+            _ => self.pre_sanitize(&text),
+        };
+        self.write_cmd(".RS 4")?;
+        self.write_cmd(".EX")?;
+        for line in styled.split_inclusive('\n') {
+            // A blank line is a paragraph break to some formatters, even in
+            // no-fill mode; `\&` keeps it a plain empty line.
+            if line == "\n" {
+                self.write_raw("\\&\n")?;
+            } else {
+                self.write_raw(line)?;
+            }
+        }
+        self.ensure_newline()?;
+        self.write_cmd(".EE")?;
+        self.write_cmd(".RE")
+    }
+
+    /// `.EX` and `.EE` are groff (and mandoc) extensions; define them the
+    /// first time they are needed, for troffs that lack them.
+    fn define_examples(&mut self) -> Result<()> {
+        if self.examples_defined {
+            return Ok(());
+        }
+        self.examples_defined = true;
+        self.write_cmd(".if !d EX \\{\\\n.de EX\n.nf\n.ft CW\n..\n.de EE\n.ft R\n.fi\n..\n.\\}")
     }
 
-    fn write_heading(&mut self, level: u8, content: &[Inline]) {
+    /// Terminal transcripts: lines starting with a `$ `, `% ` or `# ` prompt
+    /// are bold, output stays roman.
+    fn style_console(&self, text: &str) -> String {
+        let mut out = String::new();
+        for line in text.split_inclusive('\n') {
+            let (body, newline) = split_newline(line);
+            if ["$ ", "% ", "# "]
+                .iter()
+                .any(|prompt| body.starts_with(prompt))
+            {
+                out.push_str(&format!("\\fB{}\\fR", self.pre_sanitize(body)));
+            } else {
+                out.push_str(&self.pre_sanitize(body));
+            }
+            out.push_str(newline);
+        }
+        out
+    }
+
+    /// Shell scripts: keywords bold, comments italic. Quoting is tracked per
+    /// line, which is wrong for multi-line strings and right everywhere else.
+    fn style_shell(&self, text: &str) -> String {
+        let mut out = String::new();
+        for line in text.split_inclusive('\n') {
+            let (body, newline) = split_newline(line);
+            let chars = body.chars().collect::<Vec<_>>();
+            let mut quote = None;
+            let mut idx = 0;
+            while idx < chars.len() {
+                let ch = chars[idx];
+                let word_start = idx == 0 || !is_shell_word(chars[idx - 1]);
+                if quote.is_none() && ch == '#' && (idx == 0 || chars[idx - 1].is_whitespace()) {
+                    let comment = chars[idx..].iter().collect::<String>();
+                    out.push_str(&format!("\\fI{}\\fR", self.pre_sanitize(&comment)));
+                    break;
+                }
+                if quote.is_none() && word_start && is_shell_word(ch) {
+                    let end = chars[idx..]
+                        .iter()
+                        .position(|ch| !is_shell_word(*ch))
+                        .map_or(chars.len(), |len| idx + len);
+                    let word = chars[idx..end].iter().collect::<String>();
+                    if SHELL_KEYWORDS.contains(&word.as_str()) {
+                        out.push_str(&format!("\\fB{word}\\fR"));
+                    } else {
+                        out.push_str(&self.pre_sanitize(&word));
+                    }
+                    idx = end;
+                    continue;
+                }
+                match (quote, ch) {
+                    (None, '\'' | '"') => quote = Some(ch),
+                    (Some(open), _) if open == ch => quote = None,
+                    _ => {}
+                }
+                out.push_str(&self.pre_sanitize(&ch.to_string()));
+                idx += 1;
+            }
+            out.push_str(newline);
+        }
+        out
+    }
+
+    /// The number of the footnote `label`, assigning the next one if this is
+    /// its first reference.
+    fn footnote_number(&mut self, label: &str) -> usize {
+        let existing = self
+            .notes
+            .iter()
+            .position(|note| matches!(note, Note::Footnote { label: known, .. } if known == label));
+        existing.unwrap_or_else(|| {
+            self.notes.push(Note::Footnote {
+                label: label.to_string(),
+                blocks: None,
+            });
+            self.notes.len() - 1
+        }) + 1
+    }
+
+    fn define_footnote(&mut self, label: &str, blocks: &[Block<'_>]) {
+        let number = self.footnote_number(label);
+        if let Note::Footnote { blocks: slot, .. } = &mut self.notes[number - 1] {
+            *slot = Some(blocks.iter().cloned().map(Block::into_owned).collect());
+        }
+    }
+
+    /// The `NOTES` section, DocBook style: ` 1.` tags, URLs indented below
+    /// their link text. Skips the heading if the page ends in its own
+    /// `NOTES` section already.
+    fn write_notes(&mut self) -> Result<()> {
+        if self.notes.is_empty() {
+            return Ok(());
+        }
+        self.ensure_newline()?;
+        if !self.in_notes_section {
+            self.write_cmd(".SH \"NOTES\"")?;
+        }
+        // Notes may reference further notes, so the list can grow as we go.
+        let mut idx = 0;
+        while let Some(note) = self.notes.get(idx).cloned() {
+            idx += 1;
+            self.write_cmd(&format!(".IP \" {idx}.\" 4"))?;
+            match note {
+                Note::Footnote {
+                    blocks: Some(blocks),
+                    ..
+                } => self.write_blocks(&blocks, Some(ParentTag::ListItem))?,
+                Note::Footnote {
+                    label,
+                    blocks: None,
+                } => self.write_raw(&format!("{}\n", self.sanitize(&label)))?,
+                Note::Link { text, url } => {
+                    self.write_raw(&format!("{}\n", self.sanitize(&text)))?;
+                    self.write_cmd(".RS 4")?;
+                    self.write_raw(&format!("\\%{}\n", self.base_sanitize(&url)))?;
+                    self.write_cmd(".RE")?;
+                }
+            }
+            self.ensure_newline()?;
+        }
+        Ok(())
+    }
+
+    fn write_math_block(&mut self, tex: &str) -> Result<()> {
+        match self.math {
+            MathMode::Eqn => {
+                self.write_cmd(".EQ")?;
+                self.write_raw(&format!("{}\n", math::tex_to_eqn(tex)))?;
+                self.write_cmd(".EN")
+            }
+            MathMode::Text => {
+                self.write_cmd(".RS 4")?;
+                self.write_raw(&format!("{}\n", self.math_text(tex)))?;
+                self.write_cmd(".RE")
+            }
+        }
+    }
+
+    fn write_comment(&mut self, text: &str) -> Result<()> {
+        if self.comments == CommentMode::Roff {
+            self.ensure_newline()?;
+            self.pending_comments.push(text.to_string());
+            self.flush_comments()?;
+        }
+        Ok(())
+    }
+
+    /// Write comments held back from the middle of a line, one `.\"` line
+    /// per comment line. Call only at the start of a line.
+    fn flush_comments(&mut self) -> Result<()> {
+        for comment in std::mem::take(&mut self.pending_comments) {
+            for line in comment.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    self.out.write_all(b".\\\"\n")?;
+                } else {
+                    self.out.write_all(format!(".\\\" {line}\n").as_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Roff passthrough goes out untouched: no escaping, no `\&` guards.
+    fn write_raw_block(&mut self, format: &str, text: &str) -> Result<()> {
+        if format != "roff" || text.is_empty() {
+            return Ok(());
+        }
+        self.ensure_newline()?;
+        self.write_cmd(text)
+    }
+
+    fn math_text(&self, tex: &str) -> String {
+        let text = math::tex_to_text(tex, self.charset == Charset::Ascii);
+        format!("\\fI{}\\fR", self.escape_prose(&text))
+    }
+
+    /// A short centered line, which survives both troff and nroff.
+    fn write_rule(&mut self) -> Result<()> {
+        self.write_cmd(".sp")?;
+        self.write_cmd(".ce 1")?;
+        self.write_cmd("\\l'20n'")
+    }
+
+    fn write_heading(&mut self, level: u8, content: &[Inline<'_>]) -> Result<()> {
         let text = self.inline_text(content);
+        self.close_heading_indents(level)?;
         if level == 1 {
-            self.write_cmd(".SH \"NAME\"");
+            self.write_cmd(".SH \"NAME\"")?;
             let (name, desc) = self.split_name_description(&text);
             let name = self.sanitize(&name);
             let desc = self.sanitize(&desc);
             if desc.is_empty() {
-                self.write_raw(&format!("\\fB{}\\fR\n", name));
+                self.write_raw(&format!("\\fB{}\\fR\n", name))
             } else {
-                self.write_raw(&format!("\\fB{}\\fR \\- {}\n", name, desc));
+                self.write_raw(&format!("\\fB{}\\fR \\- {}\n", name, desc))
             }
         } else if level == 2 {
-            self.write_cmd(&format!(".SH \"{}\"", self.sanitize(&text)));
+            self.in_notes_section = text.trim().eq_ignore_ascii_case("NOTES");
+            self.write_cmd(&format!(".SH \"{}\"", self.sanitize(&text)))
+        } else if level == 3 {
+            self.write_cmd(&format!(".SS \"{}\"", self.h3_sanitize(&text)))
         } else {
-            self.write_cmd(&format!(".SS \"{}\"", self.h3_sanitize(&text)));
+            // man(7) has nothing below `.SS`: a bold line, with what follows
+            // indented under it until a heading at the same level or above.
+            self.write_cmd(".P")?;
+            self.write_raw(&format!("\\fB{}\\fR\n", self.sanitize(&text)))?;
+            self.write_cmd(".RS 4")?;
+            self.heading_indents.push(level);
+            Ok(())
         }
     }
 
-    fn write_list(&mut self, _kind: &ListKind, items: &[ListItem], following: &[Block]) -> usize {
+    /// End the indented content of deep headings at `level` or below it.
+    fn close_heading_indents(&mut self, level: u8) -> Result<()> {
+        while self
+            .heading_indents
+            .last()
+            .is_some_and(|open| *open >= level)
+        {
+            self.heading_indents.pop();
+            self.write_cmd(".RE")?;
+        }
+        Ok(())
+    }
+
+    fn write_list(
+        &mut self,
+        _kind: &ListKind,
+        items: &[ListItem<'_>],
+        following: &[Block<'_>],
+    ) -> Result<usize> {
         if self.is_special_list(items) {
-            self.write_cmd(".TP");
+            self.write_cmd(".TP")?;
             if let Some(item) = items.first() {
-                self.write_list_item(item);
+                match item.checked {
+                    Some(true) => self.write_raw("[x] ")?,
+                    Some(false) => self.write_raw("[ ] ")?,
+                    None => {}
+                }
+                self.write_list_item(item)?;
             }
-            self.ensure_newline();
+            self.ensure_newline()?;
             let mut consumed = 0;
             while let Some(Block::Paragraph(inlines)) = following.get(consumed) {
                 if matches!(following.get(consumed + 1), Some(Block::CodeBlock { .. })) {
                     break;
                 }
-                self.write_cmd(".IP");
-                self.write_inlines(inlines, false);
-                self.write_raw("\n");
+                self.write_cmd(".IP")?;
+                self.write_inlines(inlines, false)?;
+                self.write_raw("\n")?;
                 consumed += 1;
             }
-            consumed
+            Ok(consumed)
         } else {
             for item in items {
-                self.write_cmd(".IP \"\\(bu\" 4");
-                self.write_list_item(item);
-                self.write_raw("\n");
+                let tag = match item.checked {
+                    Some(true) => "[x]",
+                    Some(false) => "[ ]",
+                    None => "\\(bu",
+                };
+                self.write_cmd(&format!(".IP \"{tag}\" 4"))?;
+                self.write_list_item(item)?;
+                self.ensure_newline()?;
+            }
+            self.write_cmd(".IP \"\" 0")?;
+            Ok(0)
+        }
+    }
+
+    /// Each term is a `.TP` tag; further paragraphs of its definition
+    /// continue at the same indent with `.IP`.
+    fn write_definition_list(&mut self, items: &[Definition<'_>]) -> Result<()> {
+        for item in items {
+            self.write_cmd(".TP")?;
+            if !item.term.is_empty() {
+                self.write_inlines(&item.term, false)?;
+                self.write_raw("\n")?;
             }
-            if !matches!(following.first(), Some(Block::CodeBlock { .. })) {
-                self.write_cmd(".IP \"\" 0");
+            for (idx, block) in item.blocks.iter().enumerate() {
+                match block {
+                    Block::Paragraph(inlines) => {
+                        if idx > 0 {
+                            self.write_cmd(".IP")?;
+                        }
+                        self.write_inlines(inlines, false)?;
+                        self.write_raw("\n")?;
+                    }
+                    _ => {
+                        self.write_blocks(std::slice::from_ref(block), Some(ParentTag::ListItem))?
+                    }
+                }
             }
-            0
         }
+        Ok(())
     }
 
-    fn write_list_item(&mut self, item: &ListItem) {
-        if item.blocks.is_empty() {
-            return;
-        }
-        let mut blocks = item.blocks.clone();
-        if let Some(Block::Paragraph(inlines)) = blocks.first() {
-            self.write_inlines(inlines, true);
-            if blocks.len() > 1 {
-                self.write_raw("\n");
+    fn write_list_item(&mut self, item: &ListItem) -> Result<()> {
+        let mut blocks = item.blocks.as_slice();
+        if let Some((Block::Paragraph(inlines), rest)) = blocks.split_first() {
+            self.write_inlines(inlines, true)?;
+            if !rest.is_empty() {
+                self.write_raw("\n")?;
             }
-            blocks.remove(0);
+            blocks = rest;
         }
         if !blocks.is_empty() {
-            self.write_blocks(&blocks, Some(ParentTag::ListItem));
+            self.write_blocks(blocks, Some(ParentTag::ListItem))?;
         }
+        Ok(())
     }
 
-    fn ensure_newline(&mut self) {
-        if !self.output.ends_with('\n') {
-            self.output.push('\n');
+    fn ensure_newline(&mut self) -> Result<()> {
+        if !self.line_start {
+            self.write_raw("\n")?;
         }
+        Ok(())
     }
 
-    fn write_inlines(&mut self, inlines: &[Inline], in_list: bool) {
+    fn write_inlines(&mut self, inlines: &[Inline<'_>], in_list: bool) -> Result<()> {
+        self.sentence = Sentence::Open;
         for inline in inlines {
-            match inline {
-                Inline::Text(text) => self.write_raw(&self.sanitize(text)),
-                Inline::Code(text) => {
-                    let text = self.code_sanitize(text);
-                    self.write_raw(&format!("\\fB{}\\fR", text));
-                }
-                Inline::Emphasis(children) => {
-                    let text = self.inline_text(children);
-                    self.write_raw(&format!("\\fI{}\\fR", self.sanitize(&text)));
+            self.write_inline(inline, in_list)?;
+        }
+        Ok(())
+    }
+
+    fn write_inline(&mut self, inline: &Inline<'_>, in_list: bool) -> Result<()> {
+        if self.sentence_per_line {
+            return self.write_inline_by_sentence(inline, in_list);
+        }
+        self.write_inline_plain(inline, in_list)
+    }
+
+    /// Sentence-per-line variant of [`Self::write_inline_plain`]: whitespace
+    /// after a sentence end, including soft breaks and the whitespace that
+    /// separates inlines, becomes a newline.
+    fn write_inline_by_sentence(&mut self, inline: &Inline<'_>, in_list: bool) -> Result<()> {
+        match inline {
+            Inline::Text(text) => {
+                let mut text: &str = text;
+                if self.sentence == Sentence::Break
+                    || (self.sentence == Sentence::Ended && text.starts_with(char::is_whitespace))
+                {
+                    self.ensure_newline()?;
+                    text = text.trim_start();
                 }
-                Inline::Strong(children) => {
-                    let text = self.inline_text(children);
-                    self.write_raw(&format!("\\fB{}\\fR", self.sanitize(&text)));
+                let body = text.trim_end();
+                let (written, next) = if !ends_sentence(body) {
+                    (text, Sentence::Open)
+                } else if body.len() < text.len() {
+                    (body, Sentence::Break)
+                } else {
+                    (body, Sentence::Ended)
+                };
+                let escaped = self.escape_prose_with(written, true);
+                self.write_raw(&self.sanitize_angle_brackets(&escaped))?;
+                self.sentence = next;
+            }
+            Inline::LineBreak(LineBreak::Soft) if self.sentence != Sentence::Open && !in_list => {
+                self.sentence = Sentence::Break;
+            }
+            Inline::LineBreak(_) => {
+                self.write_inline_plain(inline, in_list)?;
+                self.sentence = Sentence::Open;
+            }
+            Inline::Comment(_) => self.write_inline_plain(inline, in_list)?,
+            Inline::Code(_)
+            | Inline::Superscript(_)
+            | Inline::Subscript(_)
+            | Inline::FootnoteReference(_)
+            | Inline::Math(_) => {
+                if self.sentence == Sentence::Break {
+                    self.ensure_newline()?;
                 }
-                Inline::Link { content, .. } => {
-                    let text = self.inline_text(content);
-                    self.write_raw(&self.sanitize(&text));
+                self.write_inline_plain(inline, in_list)?;
+                self.sentence = Sentence::Open;
+            }
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Link {
+                content: children, ..
+            } => {
+                if self.sentence == Sentence::Break {
+                    self.ensure_newline()?;
                 }
-                Inline::LineBreak(LineBreak::Soft) => self.write_raw(" "),
-                Inline::LineBreak(LineBreak::Hard) => {
-                    if in_list {
-                        self.write_raw("\n");
-                    } else {
-                        self.write_raw(" ");
+                self.write_inline_plain(inline, in_list)?;
+                self.sentence = if ends_sentence(&self.inline_text(children)) {
+                    Sentence::Ended
+                } else {
+                    Sentence::Open
+                };
+            }
+        }
+        Ok(())
+    }
+
+    fn write_inline_plain(&mut self, inline: &Inline<'_>, in_list: bool) -> Result<()> {
+        match inline {
+            Inline::Text(text) => self.write_raw(&self.sanitize(text))?,
+            Inline::Code(text) => {
+                let text = self.code_sanitize(text);
+                self.write_raw(&format!("\\fB{}\\fR", text))?;
+            }
+            Inline::Emphasis(children) => {
+                let text = self.inline_text(children);
+                self.write_raw(&format!("\\fI{}\\fR", self.sanitize(&text)))?;
+            }
+            Inline::Strong(children) => {
+                let text = self.inline_text(children);
+                self.write_raw(&format!("\\fB{}\\fR", self.sanitize(&text)))?;
+            }
+            Inline::Strikethrough(children) => {
+                let text = self.inline_text(children);
+                let struck = match self.strikethrough {
+                    StrikethroughStyle::Marker => {
+                        format!("\\fIdeprecated:\\fR {}", self.sanitize(&text))
                     }
+                    StrikethroughStyle::Overstrike => self.overstrike(&text),
+                };
+                self.write_raw(&struck)?;
+            }
+            Inline::Superscript(children) => {
+                let text = self.inline_text(children);
+                self.write_raw(&format!("\\u\\s-2{}\\s+2\\d", self.sanitize(&text)))?;
+            }
+            Inline::Subscript(children) => {
+                let text = self.inline_text(children);
+                self.write_raw(&format!("\\d\\s-2{}\\s+2\\u", self.sanitize(&text)))?;
+            }
+            Inline::Link { url, content, .. } => {
+                let text = self.inline_text(content);
+                self.write_raw(&self.sanitize(&text))?;
+                if self.link_notes && !url.is_empty() && !url.starts_with('#') {
+                    self.notes.push(Note::Link {
+                        text,
+                        url: url.to_string(),
+                    });
+                    self.write_raw(&format!("[{}]", self.notes.len()))?;
+                }
+            }
+            Inline::FootnoteReference(label) => {
+                let number = self.footnote_number(label);
+                self.write_raw(&format!("[{number}]"))?;
+            }
+            Inline::Math(tex) => {
+                let math = match self.math {
+                    MathMode::Eqn => format!("${}$", math::tex_to_eqn(tex)),
+                    MathMode::Text => self.math_text(tex),
+                };
+                self.write_raw(&math)?;
+            }
+            Inline::Comment(text) => {
+                if self.comments == CommentMode::Roff {
+                    self.pending_comments.push(text.to_string());
+                }
+            }
+            Inline::LineBreak(LineBreak::Soft) => self.write_raw(" ")?,
+            Inline::LineBreak(LineBreak::Hard) => {
+                if in_list {
+                    self.write_raw("\n")?;
+                } else {
+                    self.ensure_newline()?;
+                    self.write_cmd(".br")?;
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Strike `text` through character by character with `\o`, the one
+    /// overstrike that both troff and nroff understand.
+    fn overstrike(&self, text: &str) -> String {
+        let mut out = String::new();
+        for ch in text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+        {
+            match ch {
+                ' ' => out.push(' '),
+                '\'' => out.push_str("\\o'\\(aq\\(hy'"),
+                _ => out.push_str(&format!("\\o'{}\\(hy'", self.escape_prose(&ch.to_string()))),
+            }
+        }
+        out
     }
 
-    fn is_special_list(&self, items: &[ListItem]) -> bool {
+    fn is_special_list(&self, items: &[ListItem<'_>]) -> bool {
         if items.len() != 1 {
             return false;
         }
@@ -862,33 +2767,79 @@ impl RoffWriter {
         (name.to_string(), String::new())
     }
 
-    fn inline_text(&self, inlines: &[Inline]) -> String {
+    fn inline_text(&self, inlines: &[Inline<'_>]) -> String {
         inline_text(inlines)
     }
 
+    /// Escape prose. Periods and apostrophes are left alone (line starts are
+    /// protected on write), and only hyphens that introduce an option-like
+    /// token (`-v`, `--flag`) become `\-`.
     fn sanitize(&self, text: &str) -> String {
+        self.sanitize_angle_brackets(&self.escape_prose(text))
+    }
+
+    fn escape_prose(&self, text: &str) -> String {
+        self.escape_prose_with(text, false)
+    }
+
+    /// With `sentences`, whitespace after a sentence end becomes a newline
+    /// and abbreviations get a `\&` so troff does not mistake them for one.
+    fn escape_prose_with(&self, text: &str, sentences: bool) -> String {
+        let chars = text.chars().collect::<Vec<_>>();
         let mut out = String::new();
         let mut last_space = false;
-        for ch in text.chars() {
-            let chunk = match ch {
-                '\\' => "\\e".to_string(),
-                '.' => "\\.".to_string(),
-                '\'' => "\\'".to_string(),
-                '-' => "\\-".to_string(),
-                '\n' => " ".to_string(),
-                _ => ch.to_string(),
-            };
-            if ch.is_whitespace() {
-                if last_space {
-                    continue;
+        let mut option_token = false;
+        let mut idx = 0;
+        while idx < chars.len() {
+            let ch = chars[idx];
+            idx += 1;
+            if ch.is_whitespace() && ch != NBSP {
+                option_token = false;
+                if !last_space {
+                    let more = chars[idx..].iter().any(|ch| !ch.is_whitespace());
+                    if sentences && more && sentence_ends_at(&chars[..idx - 1]) {
+                        out.push('\n');
+                    } else {
+                        out.push(if ch == '\n' { ' ' } else { ch });
+                    }
                 }
                 last_space = true;
-            } else {
-                last_space = false;
+                continue;
+            }
+            last_space = false;
+            match ch {
+                '\\' => out.push_str("\\e"),
+                '$' if self.math == MathMode::Eqn => out.push_str("\\(Do"),
+                '.' if sentences
+                    && chars.get(idx).is_some_and(|next| next.is_whitespace())
+                    && is_abbreviation(&chars[..idx]) =>
+                {
+                    out.push_str(".\\&");
+                }
+                '-' if self.hyphens == HyphenPolicy::Minus => out.push_str("\\-"),
+                '-' => {
+                    if let Some(dash) = spaced_dash(&chars, idx - 1) {
+                        let escaped = special_char(dash, self.charset).unwrap_or_default();
+                        out.push_str(&escaped);
+                        idx += usize::from(dash == '\u{2014}');
+                        continue;
+                    }
+                    if !option_token && starts_option(&chars, idx - 1) {
+                        option_token = true;
+                    }
+                    if option_token {
+                        out.push_str("\\-");
+                    } else {
+                        out.push('-');
+                    }
+                }
+                _ => match special_char(ch, self.charset) {
+                    Some(escaped) => out.push_str(&escaped),
+                    None => out.push(ch),
+                },
             }
-            out.push_str(&chunk);
         }
-        self.sanitize_angle_brackets(&out)
+        out
     }
 
     fn sanitize_angle_brackets(&self, text: &str) -> String {
@@ -930,7 +2881,7 @@ impl RoffWriter {
     fn code_sanitize(&self, text: &str) -> String {
         let mut out = String::new();
         for ch in text.chars() {
-            if ch.is_whitespace() {
+            if ch.is_whitespace() && ch != NBSP {
                 out.push(' ');
             } else {
                 out.push_str(&self.base_sanitize(&ch.to_string()));
@@ -940,33 +2891,310 @@ impl RoffWriter {
     }
 
     fn h3_sanitize(&self, text: &str) -> String {
-        let base = self.base_sanitize(text);
-        base.split('\n').collect::<Vec<_>>().join(" ")
+        self.escape_prose(text)
     }
 
+    /// Escape literal text (code spans, code blocks, subsection titles):
+    /// every hyphen is a minus sign and apostrophes stay straight.
     fn base_sanitize(&self, text: &str) -> String {
         let mut out = String::new();
         for ch in text.chars() {
             match ch {
                 '\\' => out.push_str("\\e"),
-                '.' => out.push_str("\\."),
-                '\'' => out.push_str("\\'"),
+                '$' if self.math == MathMode::Eqn => out.push_str("\\(Do"),
+                '\'' => out.push_str("\\(aq"),
                 '-' => out.push_str("\\-"),
-                _ => out.push(ch),
+                _ => match special_char(ch, self.charset) {
+                    Some(escaped) => out.push_str(&escaped),
+                    None => out.push(ch),
+                },
             }
         }
         out
     }
 
-    fn write_cmd(&mut self, cmd: &str) {
-        self.output.push_str(cmd);
+    fn write_cmd(&mut self, cmd: &str) -> Result<()> {
+        if self.line_start {
+            self.flush_comments()?;
+        }
+        self.out.write_all(cmd.as_bytes())?;
         if !cmd.ends_with('\n') {
-            self.output.push('\n');
+            self.out.write_all(b"\n")?;
+        }
+        self.line_start = true;
+        Ok(())
+    }
+
+    /// Write text, protecting any line that would otherwise start with a
+    /// control character (`.` or `'`) with `\&`.
+    fn write_raw(&mut self, text: &str) -> Result<()> {
+        for segment in text.split_inclusive('\n') {
+            if self.line_start && (segment.starts_with('.') || segment.starts_with('\'')) {
+                self.out.write_all(b"\\&")?;
+            }
+            self.out.write_all(segment.as_bytes())?;
+            self.line_start = segment.ends_with('\n');
+            if self.line_start {
+                self.flush_comments()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Renderer for RoffWriter<W> {
+    fn start(&mut self, options: &ManpageOptions) -> Result<()> {
+        self.write_header(options)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.ensure_newline()?;
+        self.flush_comments()?;
+        self.close_heading_indents(1)?;
+        self.write_notes()?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn blocks(&mut self, blocks: &[Block<'_>]) -> Result<()> {
+        self.write_blocks(blocks, None)
+    }
+
+    fn heading(&mut self, level: u8, content: &[Inline<'_>]) -> Result<()> {
+        self.write_heading(level, content)
+    }
+
+    fn paragraph(&mut self, content: &[Inline<'_>]) -> Result<()> {
+        self.write_cmd(".P")?;
+        self.write_inlines(content, false)?;
+        self.write_raw("\n")?;
+        Ok(())
+    }
+
+    fn list(&mut self, kind: &ListKind, items: &[ListItem<'_>]) -> Result<()> {
+        self.write_list(kind, items, &[]).map(|_| ())
+    }
+
+    fn list_item(&mut self, item: &ListItem<'_>) -> Result<()> {
+        self.write_list_item(item)
+    }
+
+    fn code_block(&mut self, lang: Option<&str>, text: &str) -> Result<()> {
+        self.write_code_block(lang, text)
+    }
+
+    fn rule(&mut self) -> Result<()> {
+        self.write_rule()
+    }
+
+    fn footnote_definition(&mut self, label: &str, blocks: &[Block<'_>]) -> Result<()> {
+        self.define_footnote(label, blocks);
+        Ok(())
+    }
+
+    fn math(&mut self, tex: &str) -> Result<()> {
+        self.write_math_block(tex)
+    }
+
+    fn raw(&mut self, format: &str, text: &str) -> Result<()> {
+        self.write_raw_block(format, text)
+    }
+
+    fn definition_list(&mut self, items: &[Definition<'_>]) -> Result<()> {
+        self.write_definition_list(items)
+    }
+
+    fn comment(&mut self, text: &str) -> Result<()> {
+        self.write_comment(text)
+    }
+
+    fn inline(&mut self, inline: &Inline<'_>) -> Result<()> {
+        self.write_inline(inline, false)
+    }
+}
+
+const NBSP: char = '\u{a0}';
+
+/// Whether the hyphen at `idx` begins an option-like token such as `-v`,
+/// `--flag` or `-1`: it starts a word and is followed by a letter, digit or
+/// another hyphen. A hyphen ending the text on its own is a minus sign too.
+fn starts_option(chars: &[char], idx: usize) -> bool {
+    let at_word_start = idx == 0
+        || chars[idx - 1].is_whitespace()
+        || matches!(
+            chars[idx - 1],
+            '(' | '[' | '{' | '"' | '\'' | '=' | ',' | '/' | '|'
+        );
+    at_word_start
+        && chars
+            .get(idx + 1)
+            .is_none_or(|next| next.is_alphanumeric() || *next == '-')
+}
+
+/// Reserved words that `sh`/`bash` code blocks set in bold.
+const SHELL_KEYWORDS: [&str; 21] = [
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+    "in", "function", "select", "return", "export", "local", "break", "continue", "exit",
+];
+
+fn is_shell_word(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+/// Split a line from `split_inclusive('\n')` into its body and newline.
+fn split_newline(line: &str) -> (&str, &str) {
+    match line.strip_suffix('\n') {
+        Some(body) => (body, "\n"),
+        None => (line, ""),
+    }
+}
+
+/// Replace tabs with spaces up to the next multiple of `width` columns.
+fn expand_tabs(text: &str, width: usize) -> Cow<'_, str> {
+    if width == 0 || !text.contains('\t') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut column = 0;
+    for ch in text.chars() {
+        match ch {
+            '\t' => {
+                let spaces = width - column % width;
+                out.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                out.push(ch);
+                column = 0;
+            }
+            _ => {
+                out.push(ch);
+                column += 1;
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Abbreviations whose trailing period does not end a sentence.
+const ABBREVIATIONS: [&str; 9] = [
+    "e.g.", "i.e.", "etc.", "cf.", "vs.", "viz.", "al.", "approx.", "resp.",
+];
+
+/// Whether `text` ends with a sentence: `.`, `?` or `!`, optionally
+/// followed by closing quotes or brackets, and not an abbreviation.
+fn ends_sentence(text: &str) -> bool {
+    sentence_ends_at(&text.chars().collect::<Vec<_>>())
+}
+
+fn sentence_ends_at(chars: &[char]) -> bool {
+    let closers = chars
+        .iter()
+        .rev()
+        .take_while(|ch| matches!(ch, ')' | ']' | '"' | '\'' | '\u{201d}' | '\u{2019}'))
+        .count();
+    let body = &chars[..chars.len() - closers];
+    match body.last() {
+        Some('?' | '!') => true,
+        Some('.') => !is_abbreviation(body),
+        _ => false,
+    }
+}
+
+/// Whether the word ending at the end of `chars` (period included) is a
+/// known abbreviation. Lone letters are not: the `J.` in `J. Doe` reads just
+/// like the `I.` in `So do I. Then`, and a missed break is the worse mistake.
+fn is_abbreviation(chars: &[char]) -> bool {
+    let start = chars
+        .iter()
+        .rposition(|ch| ch.is_whitespace() || *ch == '(')
+        .map_or(0, |idx| idx + 1);
+    let word = chars[start..].iter().collect::<String>().to_lowercase();
+    ABBREVIATIONS.contains(&word.as_str())
+}
+
+/// A `-` or `--` with whitespace on both sides is punctuation, not an
+/// option: the former reads as an en dash, the latter as an em dash.
+fn spaced_dash(chars: &[char], idx: usize) -> Option<char> {
+    let run = chars[idx..].iter().take_while(|ch| **ch == '-').count();
+    let is_gap = |ch: Option<&char>| ch.is_some_and(|ch| ch.is_whitespace());
+    let before = idx.checked_sub(1).and_then(|prev| chars.get(prev));
+    if !is_gap(before) || !is_gap(chars.get(idx + run)) {
+        return None;
+    }
+    match run {
+        1 => Some('\u{2013}'),
+        2 => Some('\u{2014}'),
+        _ => None,
+    }
+}
+
+/// Map a character that troffs render inconsistently to a portable spelling.
+///
+/// Returns `None` for characters that can be written as-is. Roff syntax
+/// characters (`\\`, `'`, `-`) are the sanitizers' business, not ours.
+fn special_char(ch: char, charset: Charset) -> Option<Cow<'static, str>> {
+    let named = match ch {
+        '\u{2014}' => ("\\(em", "\\-\\-"),
+        '\u{2013}' => ("\\(en", "\\-"),
+        '\u{201c}' => ("\\(lq", "\""),
+        '\u{201d}' => ("\\(rq", "\""),
+        '\u{2018}' => ("\\(oq", "`"),
+        '\u{2019}' => ("\\(cq", "'"),
+        NBSP => ("\\~", "\\ "),
+        '~' => ("\\(ti", "~"),
+        '^' => ("\\(ha", "^"),
+        '\u{2022}' => ("\\(bu", "o"),
+        '\u{2026}' => ("\\&.\\|.\\|.", "..."),
+        '\u{a9}' => ("\\(co", "(C)"),
+        '\u{ae}' => ("\\(rg", "(R)"),
+        '\u{b0}' => ("\\(de", " deg"),
+        '\u{b1}' => ("\\(+-", "+/-"),
+        '\u{d7}' => ("\\(mu", "x"),
+        '\u{f7}' => ("\\(di", "/"),
+        '\u{2192}' => ("\\(->", "->"),
+        '\u{2190}' => ("\\(<-", "<-"),
+        '\u{2264}' => ("\\(<=", "<="),
+        '\u{2265}' => ("\\(>=", ">="),
+        '\u{2260}' => ("\\(!=", "!="),
+        _ if ch.is_ascii() => return None,
+        _ => {
+            return Some(match charset {
+                Charset::Groff => Cow::Owned(format!("\\[u{:04X}]", u32::from(ch))),
+                Charset::Ascii => Cow::Borrowed(ascii_fallback(ch)),
+            });
         }
+    };
+    match (charset, named) {
+        (Charset::Groff, (groff, _)) => Some(Cow::Borrowed(groff)),
+        (Charset::Ascii, (_, ascii)) if ascii.len() == 1 && ascii.starts_with(ch) => None,
+        (Charset::Ascii, (_, ascii)) => Some(Cow::Borrowed(ascii)),
     }
+}
 
-    fn write_raw(&mut self, text: &str) {
-        self.output.push_str(text);
+/// Strip diacritics from common Latin letters; anything else becomes `?`.
+fn ascii_fallback(ch: char) -> &'static str {
+    match ch {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' => "c",
+        'Ç' => "C",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' => "E",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ñ' => "n",
+        'Ñ' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => "O",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' => "Y",
+        'ß' => "ss",
+        _ => "?",
     }
 }
 
@@ -1013,7 +3241,7 @@ enum ParentTag {
 ///
 /// This is the "just do the thing" entry point for Markdown inputs.
 pub fn convert_markdown_to_roff(markdown: &str, options: &ManpageOptions) -> Result<String> {
-    let document = parse_markdown(markdown)?;
+    let document = parse_markdown_with_options(markdown, options)?;
     render_roff(&document, options)
 }
 
@@ -1021,7 +3249,9 @@ pub fn convert_markdown_to_roff(markdown: &str, options: &ManpageOptions) -> Res
 ///
 /// The YAML schema expects fields like `manpage_intro`, `body`, `sections`,
 /// `entries`, and `examples`. Example blocks are rendered as a simple
-/// transcript, because humans like narratives.
+/// transcript, because humans like narratives. A section or entry with
+/// `since`, `deprecated` or `removed` versions opens with an italic line
+/// saying so.
 pub fn convert_yaml_to_markdown(_yaml: &str) -> Result<String> {
     let docs =
         YamlLoader::load_from_str(_yaml).map_err(|err| MandateError::Yaml(err.to_string()))?;
@@ -1041,46 +3271,14 @@ pub fn convert_yaml_to_markdown(_yaml: &str) -> Result<String> {
             let section_map = ensure_mapping(section, "sections item")?;
             let title = map_get_string(section_map, "title")?.unwrap_or_default();
             out.push_str(&format!("## {}\n", title.to_uppercase()));
+            out.push_str(&version_note(section_map)?);
             out.push_str(&dedent_body(
                 &map_get_string(section_map, "body")?.unwrap_or_else(|| "\n".to_string()),
             ));
             out.push('\n');
 
             if let Some(entries) = map_get_sequence(section_map, "entries")? {
-                for entry in entries {
-                    let entry_map = ensure_mapping(entry, "entry")?;
-                    let title = map_get_string(entry_map, "title")?.unwrap_or_default();
-                    out.push_str(&format!("### {}\n", title));
-                    out.push_str(&dedent_body(
-                        &map_get_string(entry_map, "body")?.unwrap_or_else(|| "\n".to_string()),
-                    ));
-                    out.push('\n');
-
-                    if let Some(examples) = map_get_sequence(entry_map, "examples")? {
-                        out.push_str("~~~~\n");
-                        let mut first = true;
-                        for example in examples {
-                            let example_map = ensure_mapping(example, "example")?;
-                            if !first {
-                                out.push('\n');
-                            }
-                            first = false;
-                            let program =
-                                map_get_string(example_map, "program")?.unwrap_or_default();
-                            let input = map_get_string(example_map, "input")?.unwrap_or_default();
-                            out.push_str(&format!("jq '{}'\n", program));
-                            out.push_str(&format!("   {}\n", input));
-                            let outputs = map_get_sequence(example_map, "output")?;
-                            let outputs = outputs
-                                .unwrap_or(&[])
-                                .iter()
-                                .map(yaml_value_to_string)
-                                .collect::<Vec<_>>();
-                            out.push_str(&format!("=> {}\n", outputs.join(", ")));
-                        }
-                        out.push_str("~~~~\n");
-                    }
-                }
+                write_yaml_entries(&mut out, entries, 3)?;
             }
             out.push('\n');
         }
@@ -1090,6 +3288,50 @@ pub fn convert_yaml_to_markdown(_yaml: &str) -> Result<String> {
     Ok(out)
 }
 
+/// Entries become headings at `level`, their own `entries` one level down.
+/// Markdown stops at six, so anything deeper shares level 6.
+fn write_yaml_entries(out: &mut String, entries: &[Yaml], level: usize) -> Result<()> {
+    for entry in entries {
+        let entry_map = ensure_mapping(entry, "entry")?;
+        let title = map_get_string(entry_map, "title")?.unwrap_or_default();
+        out.push_str(&format!("{} {}\n", "#".repeat(level.min(6)), title));
+        out.push_str(&version_note(entry_map)?);
+        out.push_str(&dedent_body(
+            &map_get_string(entry_map, "body")?.unwrap_or_else(|| "\n".to_string()),
+        ));
+        out.push('\n');
+
+        if let Some(examples) = map_get_sequence(entry_map, "examples")? {
+            out.push_str("~~~~\n");
+            let mut first = true;
+            for example in examples {
+                let example_map = ensure_mapping(example, "example")?;
+                if !first {
+                    out.push('\n');
+                }
+                first = false;
+                let program = map_get_string(example_map, "program")?.unwrap_or_default();
+                let input = map_get_string(example_map, "input")?.unwrap_or_default();
+                out.push_str(&format!("jq '{}'\n", program));
+                out.push_str(&format!("   {}\n", input));
+                let outputs = map_get_sequence(example_map, "output")?;
+                let outputs = outputs
+                    .unwrap_or(&[])
+                    .iter()
+                    .map(yaml_value_to_string)
+                    .collect::<Vec<_>>();
+                out.push_str(&format!("=> {}\n", outputs.join(", ")));
+            }
+            out.push_str("~~~~\n");
+        }
+
+        if let Some(entries) = map_get_sequence(entry_map, "entries")? {
+            write_yaml_entries(out, entries, level + 1)?;
+        }
+    }
+    Ok(())
+}
+
 /// Convert YAML manual data directly to roff.
 pub fn convert_yaml_to_roff(yaml: &str, options: &ManpageOptions) -> Result<String> {
     let markdown = convert_yaml_to_markdown(yaml)?;
@@ -1100,17 +3342,54 @@ pub fn convert_yaml_to_roff(yaml: &str, options: &ManpageOptions) -> Result<Stri
 ///
 /// This is useful when you want to inspect or post-process the structure
 /// before rendering.
-pub fn parse_yaml_to_document(yaml: &str) -> Result<Document> {
+pub fn parse_yaml_to_document(yaml: &str) -> Result<Document<'static>> {
+    let markdown = convert_yaml_to_markdown(yaml)?;
+    parse_markdown(&markdown).map(Document::into_owned)
+}
+
+/// Parse YAML manual data into a `Document` AST, with the Markdown syntax
+/// extensions that `options` turn on.
+pub fn parse_yaml_to_document_with_options(
+    yaml: &str,
+    options: &ManpageOptions,
+) -> Result<Document<'static>> {
     let markdown = convert_yaml_to_markdown(yaml)?;
-    parse_markdown(&markdown)
+    parse_markdown_with_options(&markdown, options).map(Document::into_owned)
 }
 
 /// Render a `Document` to roff using the provided manpage options.
-pub fn render_roff(document: &Document, options: &ManpageOptions) -> Result<String> {
-    let mut writer = RoffWriter::new();
-    writer.write_header(options);
-    writer.write_blocks(&document.blocks, None);
-    Ok(writer.finish())
+pub fn render_roff(document: &Document<'_>, options: &ManpageOptions) -> Result<String> {
+    let mut out = Vec::new();
+    render_roff_to(document, options, &mut out)?;
+    String::from_utf8(out).map_err(|err| MandateError::Io(err.to_string()))
+}
+
+/// Render a `Document` as roff straight into a writer.
+///
+/// Nothing is buffered beyond what `writer` itself buffers, so wrap files and
+/// stdout in a `BufWriter` if you care about syscalls.
+pub fn render_roff_to<W: Write>(
+    document: &Document<'_>,
+    options: &ManpageOptions,
+    writer: W,
+) -> Result<()> {
+    render(document, options, &mut RoffWriter::new(writer, options))
+}
+
+/// Serialize a `Document` as pretty-printed JSON.
+///
+/// The shape mirrors the Rust types: enum variants are `snake_case` keys, so a
+/// paragraph looks like `{"paragraph": [{"text": "Hello"}]}`.
+#[cfg(feature = "serde")]
+pub fn document_to_json(document: &Document<'_>) -> Result<String> {
+    serde_json::to_string_pretty(document).map_err(|err| MandateError::Json(err.to_string()))
+}
+
+/// Deserialize a `Document` from JSON produced by [`document_to_json`] (or
+/// by anything else that speaks the same shape).
+#[cfg(feature = "serde")]
+pub fn document_from_json(json: &str) -> Result<Document<'static>> {
+    serde_json::from_str(json).map_err(|err| MandateError::Json(err.to_string()))
 }
 
 /// Validate YAML input against a schema loaded from the filesystem.
@@ -1145,6 +3424,163 @@ pub fn validate_yaml_with_schema_str(yaml: &str, schema_source: &str) -> Result<
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Input formats understood by the converters.
+pub enum InputKind {
+    /// A YAML manual (`.yml`/`.yaml`).
+    Yaml,
+    /// A Markdown manual (`.md`/`.markdown`, and anything unrecognized).
+    Markdown,
+    /// Unknown up front (stdin); try YAML first, then Markdown.
+    Auto,
+}
+
+/// Guess the input format from a path, where `-` means stdin.
+pub fn input_kind(path: &str) -> InputKind {
+    let lower = path.to_ascii_lowercase();
+    if lower == "-" {
+        return InputKind::Auto;
+    }
+    if lower.ends_with(".yaml") || lower.ends_with(".yml") {
+        return InputKind::Yaml;
+    }
+    if lower.ends_with(".md") || lower.ends_with(".markdown") {
+        return InputKind::Markdown;
+    }
+    InputKind::Markdown
+}
+
+#[derive(Debug, Clone, Default)]
+/// Renders a manpage from a build script without shelling out to the CLI.
+///
+/// The builder prints `cargo:rerun-if-changed` lines for everything it reads,
+/// includes too, so Cargo only re-runs your `build.rs` when the manual
+/// actually changed.
+///
+/// ```no_run
+/// let options = mandate::ManpageOptions::new("mytool", "1", "Mytool Manual", None, None);
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// let written = mandate::Builder::new()
+///     .input("docs/mytool.yml")
+///     .options(options)
+///     .validate(true)
+///     .write_to(out_dir)?;
+/// # Ok::<(), mandate::MandateError>(())
+/// ```
+pub struct Builder {
+    input: Option<PathBuf>,
+    options: Option<ManpageOptions>,
+    source_options: SourceOptions,
+    schema: Option<PathBuf>,
+    validate: bool,
+}
+
+impl Builder {
+    /// Create an empty builder. `input` and `options` must be set before writing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the Markdown or YAML manual to render. The format is picked from
+    /// the file extension, same as the CLI.
+    pub fn input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(path.into());
+        self
+    }
+
+    /// Set the manpage header options.
+    pub fn options(mut self, options: ManpageOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Set where included files are looked for.
+    pub fn source_options(mut self, source_options: SourceOptions) -> Self {
+        self.source_options = source_options;
+        self
+    }
+
+    /// Validate YAML input against the schema before rendering.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Validate YAML input against a schema file instead of [`BUILTIN_SCHEMA`].
+    ///
+    /// Setting a schema turns validation on.
+    pub fn schema(mut self, path: impl Into<PathBuf>) -> Self {
+        self.schema = Some(path.into());
+        self.validate = true;
+        self
+    }
+
+    /// Render the manpage into `dir` as `<program>.<section>`.
+    ///
+    /// Returns the paths written. The directory is created if needed.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let input = self
+            .input
+            .as_ref()
+            .ok_or_else(|| MandateError::Usage("builder input path is not set".to_string()))?;
+        let options = self
+            .options
+            .as_ref()
+            .ok_or_else(|| MandateError::Usage("builder options are not set".to_string()))?;
+
+        let mut dependencies = vec![input.clone()];
+        if self.validate {
+            dependencies.extend(self.schema.iter().cloned());
+        }
+        for path in &dependencies {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+
+        let source = fs::read_to_string(input)
+            .map_err(|err| MandateError::Io(format!("{}: {err}", input.display())))?;
+        let kind = input_kind(&input.to_string_lossy());
+        let resolved = resolve_includes(&source, Some(input), kind, &self.source_options)?;
+        for path in &resolved.dependencies {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+        let source = apply_profile(&resolved.text, kind, options)?;
+        let source = substitute_variables(&source, kind, &Self::variable_options(options))?;
+        let roff = match kind {
+            InputKind::Yaml => {
+                if self.validate {
+                    match &self.schema {
+                        Some(schema) => validate_yaml_with_schema(&source, schema)?,
+                        None => validate_yaml_with_schema_str(&source, BUILTIN_SCHEMA)?,
+                    }
+                }
+                convert_yaml_to_roff(&source, options)?
+            }
+            InputKind::Markdown | InputKind::Auto => convert_markdown_to_roff(&source, options)?,
+        };
+
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .map_err(|err| MandateError::Io(format!("{}: {err}", dir.display())))?;
+        let path = dir.join(format!("{}.{}", options.program, options.section));
+        fs::write(&path, roff)
+            .map_err(|err| MandateError::Io(format!("{}: {err}", path.display())))?;
+        Ok(vec![path])
+    }
+
+    /// `options`, with `{{version}}` defaulting to the version of the crate
+    /// whose build script is running.
+    fn variable_options(options: &ManpageOptions) -> ManpageOptions {
+        let mut options = options.clone();
+        if let Ok(version) = env::var("CARGO_PKG_VERSION") {
+            options
+                .variables
+                .entry("version".to_string())
+                .or_insert(version);
+        }
+        options
+    }
+}
+
 fn dedent_body(body: &str) -> String {
     body.split('\n')
         .map(|line| {
@@ -1181,6 +3617,40 @@ fn map_get_string(map: &Hash, key: &str) -> Result<Option<String>> {
     }
 }
 
+/// A version number, which YAML is keen to read as a float.
+fn map_get_version(map: &Hash, key: &str) -> Result<Option<String>> {
+    match map.get(&Yaml::String(key.to_string())) {
+        None | Some(Yaml::Null) => Ok(None),
+        Some(Yaml::String(value) | Yaml::Real(value)) => Ok(Some(value.clone())),
+        Some(Yaml::Integer(value)) => Ok(Some(value.to_string())),
+        Some(other) => Err(MandateError::Yaml(format!(
+            "expected version for key '{key}', found {}",
+            yaml_type_name(other)
+        ))),
+    }
+}
+
+/// The `*Added in 1.5, deprecated in 1.7.*` line for a section or entry,
+/// as a Markdown paragraph, or nothing.
+fn version_note(map: &Hash) -> Result<String> {
+    let mut notes = Vec::new();
+    for (key, verb) in [
+        ("since", "added"),
+        ("deprecated", "deprecated"),
+        ("removed", "removed"),
+    ] {
+        if let Some(version) = map_get_version(map, key)? {
+            notes.push(format!("{verb} in {version}"));
+        }
+    }
+    let note = notes.join(", ");
+    let mut chars = note.chars();
+    Ok(match chars.next() {
+        Some(first) => format!("\n*{}{}.*\n\n", first.to_uppercase(), chars.as_str()),
+        None => String::new(),
+    })
+}
+
 fn map_get_sequence<'a>(map: &'a Hash, key: &str) -> Result<Option<&'a [Yaml]>> {
     match map.get(&Yaml::String(key.to_string())) {
         None | Some(Yaml::Null) => Ok(None),
@@ -1274,7 +3744,7 @@ mod tests {
 
     #[test]
     fn split_name_description_variants() {
-        let writer = RoffWriter::new();
+        let writer = RoffWriter::new(io::sink(), &options());
         let (name, desc) = writer.split_name_description("mandate(1) -- Example Tool");
         assert_eq!(name, "mandate");
         assert_eq!(desc, "Example Tool");
@@ -1292,9 +3762,43 @@ mod tests {
         assert!(desc.is_empty());
     }
 
+    #[test]
+    fn special_characters_use_groff_names() {
+        let writer = RoffWriter::new(io::sink(), &options());
+        let sanitized =
+            writer.sanitize("a\u{2014}b \u{201c}q\u{201d} x\u{a0}y ~^ caf\u{e9} \u{3bb}");
+        assert_eq!(
+            sanitized,
+            "a\\(emb \\(lqq\\(rq x\\~y \\(ti\\(ha caf\\[u00E9] \\[u03BB]"
+        );
+        assert_eq!(writer.code_sanitize("~/x^2"), "\\(ti/x\\(ha2");
+    }
+
+    #[test]
+    fn ascii_charset_transliterates() {
+        let options = options().with_charset(Charset::Ascii);
+        let writer = RoffWriter::new(io::sink(), &options);
+        let sanitized =
+            writer.sanitize("a\u{2014}b \u{201c}q\u{201d} x\u{a0}y ~^ caf\u{e9} \u{3bb}");
+        assert_eq!(sanitized, "a\\-\\-b \"q\" x\\ y ~^ cafe ?");
+        assert!(sanitized.is_ascii());
+    }
+
+    #[test]
+    fn minus_hyphen_policy_escapes_every_hyphen() {
+        let options = options().with_hyphens(HyphenPolicy::Minus);
+        let writer = RoffWriter::new(io::sink(), &options);
+        assert_eq!(
+            writer.sanitize("well-known -- -v"),
+            "well\\-known \\-\\- \\-v"
+        );
+        assert_eq!("minus".parse(), Ok(HyphenPolicy::Minus));
+        assert!("dashing".parse::<HyphenPolicy>().is_err());
+    }
+
     #[test]
     fn sanitize_angle_brackets_emits_italic() {
-        let writer = RoffWriter::new();
+        let writer = RoffWriter::new(io::sink(), &options());
         let sanitized = writer.sanitize("Use <arg> and <file>");
         assert!(sanitized.contains("\\fIarg\\fR"));
         assert!(sanitized.contains("\\fIfile\\fR"));
@@ -1403,7 +3907,42 @@ See [example](https://example.com).
 .leading dot
 "#;
         let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
-        assert!(roff.contains("\\&\\.leading"));
+        assert!(roff.contains("\n\\&.leading dot\n"));
+    }
+
+    #[test]
+    fn parse_markdown_borrows_plain_text() {
+        let markdown = "plain text\n\n```\ncode\n```\n";
+        let document = parse_markdown(markdown).expect("parse");
+        match &document.blocks[..] {
+            [
+                Block::Paragraph(inlines),
+                Block::CodeBlock { lang: None, text },
+            ] => {
+                assert!(matches!(
+                    inlines[..],
+                    [Inline::Text(Cow::Borrowed("plain text"))]
+                ));
+                assert!(matches!(text, Cow::Borrowed("code\n")));
+            }
+            other => panic!("unexpected blocks: {other:?}"),
+        }
+        let owned: Document<'static> = document.into_owned();
+        assert_eq!(owned.blocks.len(), 2);
+    }
+
+    #[test]
+    fn render_roff_to_streams_protected_lines() {
+        let document = parse_markdown("## TEXT\n\n.leading dot\n").expect("parse");
+        let mut out = Vec::new();
+        render_roff_to(&document, &options(), &mut out).expect("render");
+        let roff = String::from_utf8(out).expect("utf8");
+        assert!(roff.ends_with("\\&.leading dot\n"));
+        let buffered = render_roff(&document, &options()).expect("render string");
+        assert_eq!(
+            roff.lines().skip(1).collect::<Vec<_>>(),
+            buffered.lines().skip(1).collect::<Vec<_>>()
+        );
     }
 
     #[test]
@@ -1458,6 +3997,17 @@ See [example](https://example.com).
         }
     }
 
+    #[test]
+    fn input_kind_detects_extensions() {
+        assert_eq!(input_kind("-"), InputKind::Auto);
+        assert_eq!(input_kind("manual.yaml"), InputKind::Yaml);
+        assert_eq!(input_kind("manual.yml"), InputKind::Yaml);
+        assert_eq!(input_kind("MANUAL.YML"), InputKind::Yaml);
+        assert_eq!(input_kind("manual.md"), InputKind::Markdown);
+        assert_eq!(input_kind("manual.markdown"), InputKind::Markdown);
+        assert_eq!(input_kind("manual.txt"), InputKind::Markdown);
+    }
+
     #[test]
     fn yaml_type_name_variants() {
         assert_eq!(yaml_type_name(&Yaml::Null), "null");
@@ -1,9 +1,9 @@
 #![forbid(unsafe_code)]
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Parser)]
 #[command(name = "mandate", version)]
@@ -39,6 +39,75 @@ struct Cli {
 
     #[arg(long = "schema", value_name = "PATH")]
     schema: Option<PathBuf>,
+
+    #[arg(short = 'I', long = "include-dir", value_name = "DIR")]
+    include_dirs: Vec<PathBuf>,
+
+    #[arg(short = 'D', long = "define", value_name = "NAME=VALUE", value_parser = parse_pair)]
+    defines: Vec<(String, String)>,
+
+    #[arg(long = "strict-variables")]
+    strict_variables: bool,
+
+    #[arg(long = "profile", value_name = "KEY=VALUE", value_parser = parse_pair)]
+    profile: Vec<(String, String)>,
+
+    #[arg(long = "target-version", value_name = "VERSION")]
+    target_version: Option<String>,
+
+    #[arg(long = "ascii")]
+    ascii: bool,
+
+    #[arg(long = "hyphens", value_name = "POLICY", default_value = "smart")]
+    hyphens: mandate::HyphenPolicy,
+
+    #[arg(long = "sentence-per-line")]
+    sentence_per_line: bool,
+
+    #[arg(long = "strikethrough", value_name = "STYLE", default_value = "marker")]
+    strikethrough: mandate::StrikethroughStyle,
+
+    #[arg(long = "scripts")]
+    scripts: bool,
+
+    #[arg(long = "link-notes")]
+    link_notes: bool,
+
+    #[arg(long = "math", value_name = "MODE")]
+    math: Option<mandate::MathMode>,
+
+    #[arg(long = "tab-width", value_name = "COLUMNS", default_value_t = 8)]
+    tab_width: usize,
+
+    #[arg(long = "no-merge-code-blocks")]
+    no_merge_code_blocks: bool,
+
+    #[arg(long = "comments", value_name = "MODE", default_value = "drop")]
+    comments: mandate::CommentMode,
+
+    #[arg(long = "pass", value_name = "PASS")]
+    passes: Vec<mandate::visit::Pass>,
+
+    #[arg(long = "from", value_name = "FORMAT")]
+    from: Option<InputFormat>,
+
+    #[arg(long = "format", value_name = "FORMAT", default_value = "roff")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InputFormat {
+    Markdown,
+    Yaml,
+    #[cfg(feature = "serde")]
+    AstJson,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Roff,
+    #[cfg(feature = "serde")]
+    AstJson,
 }
 
 fn read_input(path: &str) -> io::Result<String> {
@@ -51,13 +120,16 @@ fn read_input(path: &str) -> io::Result<String> {
     }
 }
 
-fn write_output(path: Option<PathBuf>, contents: &str) -> io::Result<()> {
+fn parse_pair(pair: &str) -> Result<(String, String), String> {
+    pair.split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{pair}'"))
+}
+
+fn open_output(path: Option<PathBuf>) -> io::Result<Box<dyn Write>> {
     match path {
-        Some(path) => fs::write(path, contents),
-        None => {
-            print!("{contents}");
-            Ok(())
-        }
+        Some(path) => Ok(Box::new(BufWriter::new(fs::File::create(path)?))),
+        None => Ok(Box::new(BufWriter::new(io::stdout().lock()))),
     }
 }
 
@@ -65,60 +137,99 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
     let input = read_input(&cli.input)?;
 
-    let options = mandate::ManpageOptions::new(
+    let mut options = mandate::ManpageOptions::new(
         cli.program,
         cli.section,
         cli.title,
         cli.manual_section,
         cli.source,
-    );
+    )
+    .with_charset(if cli.ascii {
+        mandate::Charset::Ascii
+    } else {
+        mandate::Charset::Groff
+    })
+    .with_hyphens(cli.hyphens)
+    .with_sentence_per_line(cli.sentence_per_line)
+    .with_strikethrough(cli.strikethrough)
+    .with_scripts(cli.scripts)
+    .with_link_notes(cli.link_notes)
+    .with_tab_width(cli.tab_width)
+    .with_merge_code_blocks(!cli.no_merge_code_blocks)
+    .with_comments(cli.comments)
+    .with_strict_variables(cli.strict_variables);
+    options.variables.extend(cli.defines);
+    options.profile.extend(cli.profile);
+    options.target_version = cli.target_version;
+    options.math = cli.math;
+
+    let kind = match cli.from {
+        Some(InputFormat::Markdown) => mandate::InputKind::Markdown,
+        Some(InputFormat::Yaml) => mandate::InputKind::Yaml,
+        #[cfg(feature = "serde")]
+        Some(InputFormat::AstJson) => {
+            let document = mandate::document_from_json(&input)?;
+            return write_document(document, &cli.passes, cli.format, &options, cli.output);
+        }
+        None => mandate::input_kind(&cli.input),
+    };
 
-    let output = match input_kind(&cli.input) {
-        InputKind::Yaml => {
+    let source_options = mandate::SourceOptions {
+        search_path: cli.include_dirs,
+    };
+    let origin = (cli.input != "-").then(|| Path::new(&cli.input));
+    let input = mandate::resolve_includes(&input, origin, kind, &source_options)?.text;
+    let input = mandate::apply_profile(&input, kind, &options)?;
+    let input = mandate::substitute_variables(&input, kind, &options)?;
+
+    let document = match kind {
+        mandate::InputKind::Yaml => {
             if cli.validate {
                 validate_yaml(&input, cli.schema.as_ref())?;
             }
-            mandate::convert_yaml_to_roff(&input, &options)?
+            mandate::parse_yaml_to_document_with_options(&input, &options)?
         }
-        InputKind::Markdown => mandate::convert_markdown_to_roff(&input, &options)?,
-        InputKind::Auto => {
+        mandate::InputKind::Markdown => mandate::parse_markdown_with_options(&input, &options)?,
+        mandate::InputKind::Auto => {
             if cli.validate {
                 match validate_yaml(&input, cli.schema.as_ref()) {
-                    Ok(()) => mandate::convert_yaml_to_roff(&input, &options)?,
+                    Ok(()) => mandate::parse_yaml_to_document_with_options(&input, &options)?,
                     Err(mandate::MandateError::Yaml(_)) => {
-                        mandate::convert_markdown_to_roff(&input, &options)?
+                        mandate::parse_markdown_with_options(&input, &options)?
                     }
                     Err(err) => return Err(Box::new(err)),
                 }
             } else {
-                mandate::convert_yaml_to_roff(&input, &options)
-                    .or_else(|_| mandate::convert_markdown_to_roff(&input, &options))?
+                mandate::parse_yaml_to_document_with_options(&input, &options)
+                    .or_else(|_| mandate::parse_markdown_with_options(&input, &options))?
             }
         }
     };
-    write_output(cli.output, &output)?;
-    Ok(())
+    write_document(document, &cli.passes, cli.format, &options, cli.output)
 }
 
-#[derive(Debug, Clone, Copy)]
-enum InputKind {
-    Yaml,
-    Markdown,
-    Auto,
+fn write_document(
+    mut document: mandate::Document<'_>,
+    passes: &[mandate::visit::Pass],
+    format: OutputFormat,
+    options: &mandate::ManpageOptions,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    mandate::visit::apply_passes(&mut document, passes);
+    let output = open_output(output)?;
+    match format {
+        OutputFormat::Roff => mandate::render_roff_to(&document, options, output)?,
+        #[cfg(feature = "serde")]
+        OutputFormat::AstJson => write_json(&document, output)?,
+    }
+    Ok(())
 }
 
-fn input_kind(path: &str) -> InputKind {
-    let lower = path.to_ascii_lowercase();
-    if lower == "-" {
-        return InputKind::Auto;
-    }
-    if lower.ends_with(".yaml") || lower.ends_with(".yml") {
-        return InputKind::Yaml;
-    }
-    if lower.ends_with(".md") || lower.ends_with(".markdown") {
-        return InputKind::Markdown;
-    }
-    InputKind::Markdown
+#[cfg(feature = "serde")]
+fn write_json(document: &mandate::Document<'_>, mut output: Box<dyn Write>) -> io::Result<()> {
+    let json = mandate::document_to_json(document).map_err(io::Error::other)?;
+    writeln!(output, "{json}")?;
+    output.flush()
 }
 
 fn validate_yaml(input: &str, schema: Option<&PathBuf>) -> Result<(), mandate::MandateError> {
@@ -127,18 +238,3 @@ fn validate_yaml(input: &str, schema: Option<&PathBuf>) -> Result<(), mandate::M
         None => mandate::validate_yaml_with_schema_str(input, mandate::BUILTIN_SCHEMA),
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn input_kind_detects_extensions() {
-        assert!(matches!(input_kind("-"), InputKind::Auto));
-        assert!(matches!(input_kind("manual.yaml"), InputKind::Yaml));
-        assert!(matches!(input_kind("manual.yml"), InputKind::Yaml));
-        assert!(matches!(input_kind("manual.md"), InputKind::Markdown));
-        assert!(matches!(input_kind("manual.markdown"), InputKind::Markdown));
-        assert!(matches!(input_kind("manual.txt"), InputKind::Markdown));
-    }
-}
@@ -0,0 +1,296 @@
+//! Conversion of the simple end of TeX math: fractions, square roots,
+//! sub/superscripts, Greek letters and a handful of operators. Anything
+//! fancier comes out as its command name, which is at least legible.
+
+/// Greek letters and operators: TeX name, eqn spelling, Unicode, ASCII.
+const SYMBOLS: [(&str, &str, &str, &str); 45] = [
+    ("alpha", "alpha", "\u{3b1}", "alpha"),
+    ("beta", "beta", "\u{3b2}", "beta"),
+    ("gamma", "gamma", "\u{3b3}", "gamma"),
+    ("delta", "delta", "\u{3b4}", "delta"),
+    ("epsilon", "epsilon", "\u{3b5}", "epsilon"),
+    ("zeta", "zeta", "\u{3b6}", "zeta"),
+    ("eta", "eta", "\u{3b7}", "eta"),
+    ("theta", "theta", "\u{3b8}", "theta"),
+    ("iota", "iota", "\u{3b9}", "iota"),
+    ("kappa", "kappa", "\u{3ba}", "kappa"),
+    ("lambda", "lambda", "\u{3bb}", "lambda"),
+    ("mu", "mu", "\u{3bc}", "mu"),
+    ("nu", "nu", "\u{3bd}", "nu"),
+    ("xi", "xi", "\u{3be}", "xi"),
+    ("pi", "pi", "\u{3c0}", "pi"),
+    ("rho", "rho", "\u{3c1}", "rho"),
+    ("sigma", "sigma", "\u{3c3}", "sigma"),
+    ("tau", "tau", "\u{3c4}", "tau"),
+    ("phi", "phi", "\u{3c6}", "phi"),
+    ("chi", "chi", "\u{3c7}", "chi"),
+    ("psi", "psi", "\u{3c8}", "psi"),
+    ("omega", "omega", "\u{3c9}", "omega"),
+    ("Gamma", "GAMMA", "\u{393}", "Gamma"),
+    ("Delta", "DELTA", "\u{394}", "Delta"),
+    ("Theta", "THETA", "\u{398}", "Theta"),
+    ("Lambda", "LAMBDA", "\u{39b}", "Lambda"),
+    ("Pi", "PI", "\u{3a0}", "Pi"),
+    ("Sigma", "SIGMA", "\u{3a3}", "Sigma"),
+    ("Phi", "PHI", "\u{3a6}", "Phi"),
+    ("Psi", "PSI", "\u{3a8}", "Psi"),
+    ("Omega", "OMEGA", "\u{3a9}", "Omega"),
+    ("cdot", "cdot", "\u{b7}", "*"),
+    ("times", "times", "\u{d7}", "x"),
+    ("div", "\\(di", "\u{f7}", "/"),
+    ("pm", "+-", "\u{b1}", "+/-"),
+    ("le", "<=", "\u{2264}", "<="),
+    ("leq", "<=", "\u{2264}", "<="),
+    ("ge", ">=", "\u{2265}", ">="),
+    ("geq", ">=", "\u{2265}", ">="),
+    ("ne", "!=", "\u{2260}", "!="),
+    ("approx", "approx", "\u{2248}", "~="),
+    ("to", "->", "\u{2192}", "->"),
+    ("infty", "inf", "\u{221e}", "infinity"),
+    ("sum", "sum", "\u{2211}", "sum"),
+    ("int", "int", "\u{222b}", "integral"),
+];
+
+/// Convert TeX to eqn(1) input.
+pub(crate) fn tex_to_eqn(tex: &str) -> String {
+    let chars = tex.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+    let mut out = String::new();
+    while idx < chars.len() {
+        eqn_atom(&chars, &mut idx, &mut out);
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn eqn_atom(chars: &[char], idx: &mut usize, out: &mut String) {
+    let ch = chars[*idx];
+    *idx += 1;
+    match ch {
+        '\\' => {
+            let name = command(chars, idx);
+            match name.as_str() {
+                "frac" => {
+                    let num = eqn_arg(chars, idx);
+                    let den = eqn_arg(chars, idx);
+                    out.push_str(&format!(" {{{num}}} over {{{den}}} "));
+                }
+                "sqrt" => {
+                    let arg = eqn_arg(chars, idx);
+                    out.push_str(&format!(" sqrt {{{arg}}} "));
+                }
+                "," | ";" | ":" | " " => out.push_str(" ~ "),
+                "!" => {}
+                _ if !name.starts_with(|ch: char| ch.is_ascii_alphabetic()) => {
+                    out.push_str(&format!(" \"{name}\" "));
+                }
+                _ => {
+                    let symbol = SYMBOLS.iter().find(|symbol| symbol.0 == name);
+                    out.push(' ');
+                    out.push_str(symbol.map_or(name.as_str(), |symbol| symbol.1));
+                    out.push(' ');
+                }
+            }
+        }
+        '^' | '_' => {
+            let arg = eqn_arg(chars, idx);
+            let op = if ch == '^' { "sup" } else { "sub" };
+            out.push_str(&format!(" {op} {{{arg}}} "));
+        }
+        '{' => {
+            let inner = group(chars, idx);
+            out.push_str(&format!(" {{{}}} ", tex_to_eqn(&inner)));
+        }
+        _ => out.push(ch),
+    }
+}
+
+/// The next argument (a `{group}`, a command or a single character),
+/// converted.
+fn eqn_arg(chars: &[char], idx: &mut usize) -> String {
+    skip_spaces(chars, idx);
+    match chars.get(*idx) {
+        Some('{') => {
+            *idx += 1;
+            tex_to_eqn(&group(chars, idx))
+        }
+        Some(_) => {
+            let mut out = String::new();
+            eqn_atom(chars, idx, &mut out);
+            out.trim().to_string()
+        }
+        None => String::new(),
+    }
+}
+
+/// Convert TeX to readable text: Unicode symbols, or ASCII words when
+/// `ascii` is set. Fractions become `a/b`, scripts `x^2` and `x_i` (or
+/// Unicode digits where there are some).
+pub(crate) fn tex_to_text(tex: &str, ascii: bool) -> String {
+    let chars = tex.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+    let mut out = String::new();
+    while idx < chars.len() {
+        text_atom(&chars, &mut idx, &mut out, ascii);
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn text_atom(chars: &[char], idx: &mut usize, out: &mut String, ascii: bool) {
+    let ch = chars[*idx];
+    *idx += 1;
+    match ch {
+        '\\' => {
+            let name = command(chars, idx);
+            match name.as_str() {
+                "frac" => {
+                    let num = text_arg(chars, idx, ascii);
+                    let den = text_arg(chars, idx, ascii);
+                    out.push_str(&format!("{}/{}", parenthesize(&num), parenthesize(&den)));
+                }
+                "sqrt" => {
+                    let arg = text_arg(chars, idx, ascii);
+                    let root = if ascii { "sqrt" } else { "\u{221a}" };
+                    out.push_str(&format!("{root}({arg})"));
+                }
+                "," | ";" | ":" | " " => out.push(' '),
+                "!" => {}
+                _ => match SYMBOLS.iter().find(|symbol| symbol.0 == name) {
+                    Some(symbol) if ascii => out.push_str(&format!(" {} ", symbol.3)),
+                    Some(symbol) => out.push_str(symbol.2),
+                    None => out.push_str(&name),
+                },
+            }
+        }
+        '^' | '_' => {
+            let arg = text_arg(chars, idx, ascii);
+            let digits = if ch == '^' {
+                "\u{2070}\u{b9}\u{b2}\u{b3}\u{2074}\u{2075}\u{2076}\u{2077}\u{2078}\u{2079}"
+            } else {
+                "\u{2080}\u{2081}\u{2082}\u{2083}\u{2084}\u{2085}\u{2086}\u{2087}\u{2088}\u{2089}"
+            };
+            if !ascii && !arg.is_empty() && arg.chars().all(|ch| ch.is_ascii_digit()) {
+                let digits = digits.chars().collect::<Vec<_>>();
+                out.extend(arg.chars().map(|ch| digits[ch as usize - '0' as usize]));
+            } else {
+                out.push(ch);
+                out.push_str(&parenthesize(&arg));
+            }
+        }
+        '{' => {
+            let inner = group(chars, idx);
+            out.push_str(&tex_to_text(&inner, ascii));
+        }
+        _ => out.push(ch),
+    }
+}
+
+fn text_arg(chars: &[char], idx: &mut usize, ascii: bool) -> String {
+    skip_spaces(chars, idx);
+    match chars.get(*idx) {
+        Some('{') => {
+            *idx += 1;
+            tex_to_text(&group(chars, idx), ascii)
+        }
+        Some(_) => {
+            let mut out = String::new();
+            text_atom(chars, idx, &mut out, ascii);
+            out.trim().to_string()
+        }
+        None => String::new(),
+    }
+}
+
+fn parenthesize(text: &str) -> String {
+    if text.chars().all(char::is_alphanumeric) {
+        text.to_string()
+    } else {
+        format!("({text})")
+    }
+}
+
+/// Read a command name after its backslash: a run of letters, or a single
+/// other character (`\{`, `\,`).
+fn command(chars: &[char], idx: &mut usize) -> String {
+    let start = *idx;
+    while chars.get(*idx).is_some_and(|ch| ch.is_ascii_alphabetic()) {
+        *idx += 1;
+    }
+    if *idx == start && *idx < chars.len() {
+        *idx += 1;
+    }
+    chars[start..*idx].iter().collect()
+}
+
+/// Read up to the brace closing a group whose `{` was just consumed.
+fn group(chars: &[char], idx: &mut usize) -> String {
+    let mut depth = 1;
+    let mut out = String::new();
+    while let Some(&ch) = chars.get(*idx) {
+        *idx += 1;
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn skip_spaces(chars: &[char], idx: &mut usize) {
+    while chars.get(*idx).is_some_and(|ch| ch.is_whitespace()) {
+        *idx += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(tex_to_text(r"\frac{a}{b}", false), "a/b");
+        assert_eq!(tex_to_text(r"\frac{n+1}{2}", false), "(n+1)/2");
+        assert_eq!(tex_to_text(r"\sqrt{2}", false), "\u{221a}(2)");
+        assert_eq!(tex_to_text(r"\sqrt{2}", true), "sqrt(2)");
+        assert_eq!(tex_to_eqn(r"\frac{n+1}{2}"), "{n+1} over {2}");
+        assert_eq!(tex_to_eqn(r"\sqrt 2"), "sqrt {2}");
+    }
+
+    #[test]
+    fn sub_and_superscripts() {
+        assert_eq!(
+            tex_to_text("x^2 + x_{10}", false),
+            "x\u{b2} + x\u{2081}\u{2080}"
+        );
+        assert_eq!(tex_to_text("x^{n+1} x_i", false), "x^(n+1) x_i");
+        assert_eq!(tex_to_text("x^2", true), "x^2");
+        assert_eq!(tex_to_eqn("x^{n+1} x_i"), "x sup {n+1} x sub {i}");
+    }
+
+    #[test]
+    fn greek_letters_and_operators() {
+        assert_eq!(
+            tex_to_text(r"\alpha \le \Omega", false),
+            "\u{3b1} \u{2264} \u{3a9}"
+        );
+        assert_eq!(tex_to_text(r"\alpha \le \Omega", true), "alpha <= Omega");
+        assert_eq!(
+            tex_to_eqn(r"\alpha \le \Omega \div 2"),
+            r"alpha <= OMEGA \(di 2"
+        );
+    }
+
+    #[test]
+    fn unknown_commands_keep_their_names() {
+        assert_eq!(tex_to_text(r"\lfloor x \rfloor", false), "lfloor x rfloor");
+        assert_eq!(tex_to_eqn(r"\lfloor x \rfloor"), "lfloor x rfloor");
+        assert_eq!(tex_to_eqn(r"\{x\}"), r#""{" x "}""#);
+        assert_eq!(tex_to_text(r"\{x\}", false), "{x}");
+    }
+}
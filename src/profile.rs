@@ -0,0 +1,285 @@
+//! Conditional content, selected by profile before anything is parsed.
+//!
+//! Markdown wraps content in `<!-- if platform=linux -->` ...
+//! `<!-- else -->` ... `<!-- endif -->` lines; YAML sections, entries and
+//! other list items take an `only: [linux]` key. Both are checked against
+//! [`ManpageOptions::profile`].
+//!
+//! With [`ManpageOptions::target_version`] set, YAML list items are also
+//! left out when their `since` version is newer than the target or their
+//! `removed` version is not.
+//!
+//! A condition is a term, or several separated by spaces that must all hold:
+//! `key=value` (or `key=a,b` for either), `key!=value`, or a bare `value`
+//! that matches any profile key set to it. In an `only:` list, any term
+//! will do. A key missing from the profile has no value: `key=value` fails
+//! and `key!=value` holds.
+
+use crate::include::{Fences, emit_yaml, is_yaml};
+use crate::{InputKind, MandateError, ManpageOptions, Result};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Keep only the content that applies to `options.profile` and
+/// `options.target_version`.
+///
+/// Text without conditionals is returned untouched.
+pub fn apply_profile(source: &str, kind: InputKind, options: &ManpageOptions) -> Result<String> {
+    if !source.contains("<!--") && !source.contains("only") && options.target_version.is_none() {
+        return Ok(source.to_string());
+    }
+    if !is_yaml(source, kind) {
+        return markdown(source, &options.profile);
+    }
+    let docs =
+        YamlLoader::load_from_str(source).map_err(|err| MandateError::Yaml(err.to_string()))?;
+    let root = docs.into_iter().next().unwrap_or(Yaml::Null);
+    emit_yaml(&yaml(root, options)?)
+}
+
+struct Branch {
+    /// Whether the enclosing branches are all being kept.
+    outer: bool,
+    condition: bool,
+    in_else: bool,
+    /// Index of the `if` line.
+    start: usize,
+}
+
+impl Branch {
+    fn keeps(&self) -> bool {
+        self.outer && self.condition != self.in_else
+    }
+}
+
+fn markdown(source: &str, profile: &BTreeMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(source.len());
+    let mut branches: Vec<Branch> = Vec::new();
+    let mut fences = Fences::default();
+    for (idx, line) in source.split_inclusive('\n').enumerate() {
+        let keep = branches.last().is_none_or(Branch::keeps);
+        let trimmed = line.trim();
+        // Fences in a dropped branch still count, or an `endif` inside one
+        // would go unseen.
+        if fences.contains(trimmed) {
+            if keep {
+                out.push_str(line);
+            }
+            continue;
+        }
+        match directive(trimmed) {
+            Some(Directive::If(condition)) => {
+                let condition =
+                    condition_holds(condition, profile).map_err(|err| markdown_error(idx, &err))?;
+                branches.push(Branch {
+                    outer: keep,
+                    condition,
+                    in_else: false,
+                    start: idx,
+                });
+            }
+            Some(Directive::Else) => match branches.last_mut() {
+                Some(branch) if !branch.in_else => branch.in_else = true,
+                Some(_) => return Err(markdown_error(idx, "a second <!-- else -->")),
+                None => return Err(markdown_error(idx, "<!-- else --> without <!-- if -->")),
+            },
+            Some(Directive::EndIf) => {
+                branches
+                    .pop()
+                    .ok_or_else(|| markdown_error(idx, "<!-- endif --> without <!-- if -->"))?;
+            }
+            None if keep => out.push_str(line),
+            None => {}
+        }
+    }
+    match branches.last() {
+        Some(branch) => Err(markdown_error(
+            branch.start,
+            "<!-- if --> without <!-- endif -->",
+        )),
+        None => Ok(out),
+    }
+}
+
+fn markdown_error(idx: usize, msg: &str) -> MandateError {
+    MandateError::Markdown(format!("line {}: {msg}", idx + 1))
+}
+
+enum Directive<'a> {
+    If(&'a str),
+    Else,
+    EndIf,
+}
+
+fn directive(line: &str) -> Option<Directive<'_>> {
+    let body = line.strip_prefix("<!--")?.strip_suffix("-->")?.trim();
+    match body {
+        "else" => Some(Directive::Else),
+        "endif" => Some(Directive::EndIf),
+        _ => body
+            .strip_prefix("if")
+            .filter(|condition| condition.is_empty() || condition.starts_with(char::is_whitespace))
+            .map(|condition| Directive::If(condition.trim())),
+    }
+}
+
+/// Drop list items whose `only:` does not match or whose versions rule
+/// them out, and the `only:` keys of the rest. Mappings outside lists, like
+/// `meta:`, keep any `only` key they have.
+fn yaml(value: Yaml, options: &ManpageOptions) -> Result<Yaml> {
+    let only = Yaml::String("only".to_string());
+    match value {
+        Yaml::Array(items) => {
+            let mut kept = Vec::with_capacity(items.len());
+            for item in items {
+                let item = match item {
+                    Yaml::Hash(mut map) => {
+                        if let Some(terms) = map.remove(&only) {
+                            if !only_holds(&terms, &options.profile)? {
+                                continue;
+                            }
+                        }
+                        if let Some(target) = &options.target_version {
+                            if !in_release(&map, target)? {
+                                continue;
+                            }
+                        }
+                        Yaml::Hash(map)
+                    }
+                    other => other,
+                };
+                kept.push(yaml(item, options)?);
+            }
+            Ok(Yaml::Array(kept))
+        }
+        Yaml::Hash(map) => Ok(Yaml::Hash(
+            map.into_iter()
+                .map(|(key, value)| Ok((key, yaml(value, options)?)))
+                .collect::<Result<_>>()?,
+        )),
+        Yaml::String(text) if text.contains("<!--") => {
+            Ok(Yaml::String(markdown(&text, &options.profile)?))
+        }
+        other => Ok(other),
+    }
+}
+
+/// Whether an item with `since` and `removed` versions exists in `target`.
+fn in_release(map: &yaml_rust2::yaml::Hash, target: &str) -> Result<bool> {
+    let version = |key: &str| match map.get(&Yaml::String(key.to_string())) {
+        None | Some(Yaml::Null) => Ok(None),
+        Some(Yaml::String(version) | Yaml::Real(version)) => Ok(Some(version.clone())),
+        Some(Yaml::Integer(version)) => Ok(Some(version.to_string())),
+        Some(_) => Err(MandateError::Yaml(format!("{key}: expected a version"))),
+    };
+    let added = version("since")?.is_none_or(|since| compare_versions(&since, target).is_le());
+    let gone =
+        version("removed")?.is_some_and(|removed| compare_versions(&removed, target).is_le());
+    Ok(added && !gone)
+}
+
+/// Compare dotted versions part by part, numerically where both parts are
+/// numbers: `1.10` is newer than `1.9`, and `1.5` is `1.5.0`. A pre-release
+/// (`1.7-rc1`, `1.7rc1`) comes before the release it leads up to.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, a_pre) = split_pre_release(a);
+    let (b, b_pre) = split_pre_release(b);
+    compare_parts(a, b).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_parts(a, b),
+    })
+}
+
+/// Split `v1.7-rc1` into the release `1.7` and the pre-release `rc1`.
+fn split_pre_release(version: &str) -> (&str, Option<&str>) {
+    let version = version.trim().trim_start_matches('v');
+    match version.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(idx) => (
+            version[..idx].trim_end_matches('.'),
+            Some(version[idx..].trim_start_matches(['-', '.'])),
+        ),
+        None => (version, None),
+    }
+}
+
+/// Compare the parts of two versions; `rc10` is the parts `rc` and `10`.
+fn compare_parts(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<String> {
+        let mut parts: Vec<String> = Vec::new();
+        for part in version.split(['.', '-']) {
+            let mut rest = part;
+            while let Some(ch) = rest.chars().next() {
+                let digits = ch.is_ascii_digit();
+                let end = rest
+                    .find(|c: char| c.is_ascii_digit() != digits)
+                    .unwrap_or(rest.len());
+                parts.push(rest[..end].to_string());
+                rest = &rest[end..];
+            }
+        }
+        while parts.len() > 1 && parts.last().is_some_and(|part| part == "0") {
+            parts.pop();
+        }
+        parts
+    };
+    let (a, b) = (parts(a), parts(b));
+    for (x, y) in a.iter().zip(&b) {
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if order.is_ne() {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+fn only_holds(terms: &Yaml, profile: &BTreeMap<String, String>) -> Result<bool> {
+    let error = |msg: String| MandateError::Yaml(format!("only: {msg}"));
+    match terms {
+        Yaml::String(term) => condition_holds(term, profile).map_err(error),
+        Yaml::Array(terms) => {
+            for term in terms {
+                let Yaml::String(term) = term else {
+                    return Err(error("expected a list of strings".to_string()));
+                };
+                if condition_holds(term, profile).map_err(error)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        _ => Err(error("expected a string or a list of strings".to_string())),
+    }
+}
+
+fn condition_holds(
+    condition: &str,
+    profile: &BTreeMap<String, String>,
+) -> std::result::Result<bool, String> {
+    let mut terms = condition.split_whitespace().peekable();
+    if terms.peek().is_none() {
+        return Err("empty condition".to_string());
+    }
+    for term in terms {
+        let holds = if let Some((key, values)) = term.split_once("!=") {
+            profile
+                .get(key)
+                .is_none_or(|value| !values.split(',').any(|v| v == value))
+        } else if let Some((key, values)) = term.split_once('=') {
+            profile
+                .get(key)
+                .is_some_and(|value| values.split(',').any(|v| v == value))
+        } else {
+            profile.values().any(|value| value == term)
+        };
+        if !holds {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
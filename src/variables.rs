@@ -0,0 +1,248 @@
+//! `{{name}}` substitution, run on manual sources before they are parsed.
+//!
+//! Values come from, lowest precedence first: the built-ins (`program`,
+//! `section`, `title`, `date`, and `manual_section` and `source` when set),
+//! Markdown front matter or a YAML manual's `meta:` mapping, and
+//! [`ManpageOptions::variables`]. Values may refer to other variables, one
+//! level deep. `{{env.NAME}}` reads an environment variable. `\{{` is a
+//! literal `{{`. Fenced code blocks are left as written, since a template
+//! example there is not meant for us.
+
+use crate::include::{Fences, emit_yaml, is_yaml};
+use crate::{InputKind, MandateError, ManpageOptions, Result, manpage_date};
+use std::collections::BTreeMap;
+use std::env;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Replace `{{name}}` references in `source` with their values.
+///
+/// Unknown names are left as written, or are an error when
+/// [`ManpageOptions::strict_variables`] is set. Markdown front matter is
+/// consumed, whether or not the page uses it; YAML is substituted value by
+/// value, so a value full of colons cannot break the document. Other text
+/// without `{{` is returned untouched.
+pub fn substitute_variables(
+    source: &str,
+    kind: InputKind,
+    options: &ManpageOptions,
+) -> Result<String> {
+    let front_matter = match kind {
+        InputKind::Yaml => None,
+        InputKind::Markdown | InputKind::Auto => front_matter(source)?,
+    };
+    if let Some((meta, body)) = front_matter {
+        let variables = variables(options, meta)?;
+        return Substitution::new(&variables, options).text(body);
+    }
+    if !source.contains("{{") {
+        return Ok(source.to_string());
+    }
+    if !is_yaml(source, kind) {
+        let variables = variables(options, BTreeMap::new())?;
+        return Substitution::new(&variables, options).text(source);
+    }
+
+    let docs =
+        YamlLoader::load_from_str(source).map_err(|err| MandateError::Yaml(err.to_string()))?;
+    let root = docs.into_iter().next().unwrap_or(Yaml::Null);
+    let meta = match root
+        .as_hash()
+        .and_then(|map| map.get(&Yaml::String("meta".to_string())))
+    {
+        Some(meta) => scalar_map(meta, "meta")?,
+        None => BTreeMap::new(),
+    };
+    let variables = variables(options, meta)?;
+    let root = Substitution::new(&variables, options).yaml(root)?;
+    emit_yaml(&root)
+}
+
+/// Every variable in scope, with references in their values substituted.
+fn variables(
+    options: &ManpageOptions,
+    meta: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>> {
+    let mut variables = builtin_variables(options);
+    variables.extend(meta);
+    variables.extend(options.variables.clone());
+    let substitution = Substitution::new(&variables, options);
+    variables
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), substitution.text(value)?)))
+        .collect()
+}
+
+fn builtin_variables(options: &ManpageOptions) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::from([
+        ("program".to_string(), options.program.clone()),
+        ("section".to_string(), options.section.clone()),
+        ("title".to_string(), options.title.clone()),
+        ("date".to_string(), manpage_date()),
+    ]);
+    if let Some(manual_section) = &options.manual_section {
+        variables.insert("manual_section".to_string(), manual_section.clone());
+    }
+    if let Some(source) = &options.source {
+        variables.insert("source".to_string(), source.clone());
+    }
+    variables
+}
+
+fn front_matter(source: &str) -> Result<Option<(BTreeMap<String, String>, &str)>> {
+    split_front_matter(source)
+        .map(|(meta, body)| Ok((scalar_map(&meta, "front matter")?, body)))
+        .transpose()
+}
+
+/// Split `---` front matter off a Markdown source, as the mapping and the
+/// body after it. Anything that does not close or does not load as a mapping
+/// is left for the Markdown parser (it may well be a thematic break).
+pub(crate) fn split_front_matter(source: &str) -> Option<(Yaml, &str)> {
+    let rest = source
+        .strip_prefix("---\n")
+        .or_else(|| source.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            let meta = YamlLoader::load_from_str(yaml).ok()?.into_iter().next()?;
+            return matches!(meta, Yaml::Hash(_)).then_some((meta, body));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// A mapping of names to strings, numbers or booleans.
+fn scalar_map(value: &Yaml, what: &str) -> Result<BTreeMap<String, String>> {
+    let Yaml::Hash(map) = value else {
+        return Err(MandateError::Yaml(format!("{what} must be a mapping")));
+    };
+    map.iter()
+        .map(|(key, value)| {
+            let key = key
+                .as_str()
+                .ok_or_else(|| MandateError::Yaml(format!("{what} keys must be strings")))?;
+            let value = match value {
+                Yaml::String(text) | Yaml::Real(text) => text.clone(),
+                Yaml::Integer(number) => number.to_string(),
+                Yaml::Boolean(flag) => flag.to_string(),
+                _ => {
+                    return Err(MandateError::Yaml(format!(
+                        "{what} value for '{key}' must be a string, number or boolean"
+                    )));
+                }
+            };
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+struct Substitution<'v> {
+    variables: &'v BTreeMap<String, String>,
+    strict: bool,
+}
+
+impl<'v> Substitution<'v> {
+    fn new(variables: &'v BTreeMap<String, String>, options: &ManpageOptions) -> Self {
+        Self {
+            variables,
+            strict: options.strict_variables,
+        }
+    }
+
+    fn yaml(&self, value: Yaml) -> Result<Yaml> {
+        Ok(match value {
+            Yaml::String(text) if text.contains("{{") => Yaml::String(self.text(&text)?),
+            Yaml::Array(items) => Yaml::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.yaml(item))
+                    .collect::<Result<_>>()?,
+            ),
+            Yaml::Hash(map) => Yaml::Hash(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, self.yaml(value)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            other => other,
+        })
+    }
+
+    /// Substitute `text`, copying fenced code blocks through untouched.
+    fn text(&self, text: &str) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut fences = Fences::default();
+        let (mut start, mut offset) = (0, 0);
+        for line in text.split_inclusive('\n') {
+            if fences.contains(line.trim()) {
+                out.push_str(&self.prose(&text[start..offset])?);
+                out.push_str(line);
+                start = offset + line.len();
+            }
+            offset += line.len();
+        }
+        out.push_str(&self.prose(&text[start..])?);
+        Ok(out)
+    }
+
+    fn prose(&self, text: &str) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                out.push_str(&rest[start..]);
+                return Ok(out);
+            };
+            let name = after[..end].trim();
+            match self.lookup(name) {
+                Some(value) => out.push_str(&value),
+                None if self.strict && is_name(name) => {
+                    return Err(MandateError::Variable(format!(
+                        "undefined variable '{name}' in \"{}\"",
+                        line_around(text, text.len() - rest.len() + start)
+                    )));
+                }
+                None => out.push_str(&rest[start..start + 2 + end + 2]),
+            }
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        if !is_name(name) {
+            return None;
+        }
+        match name.strip_prefix("env.") {
+            Some(var) => env::var(var).ok(),
+            None => self.variables.get(name).cloned(),
+        }
+    }
+}
+
+/// Variable names are identifiers, possibly dotted or dashed; anything else
+/// between braces (`{{ a + b }}`, `{{#each}}`) is someone else's template.
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// The trimmed line containing byte `at`, for error messages.
+fn line_around(text: &str, at: usize) -> &str {
+    let start = text[..at].rfind('\n').map_or(0, |idx| idx + 1);
+    let end = text[at..].find('\n').map_or(text.len(), |idx| at + idx);
+    text[start..end].trim()
+}
@@ -0,0 +1,385 @@
+//! Traversal and transformation passes over the [`Document`] AST.
+//!
+//! [`Visitor`] walks a document read-only, [`VisitorMut`] walks it mutably.
+//! Every `visit_*` method defaults to the matching `walk_*` function, so an
+//! implementation overrides the nodes it cares about and calls `walk_*` to
+//! keep descending.
+//!
+//! ```
+//! use mandate::visit::VisitorMut;
+//! use mandate::{Block, Inline};
+//! use std::borrow::Cow;
+//!
+//! /// Append a standard ENVIRONMENT section to every page.
+//! struct Environment;
+//!
+//! impl VisitorMut for Environment {
+//!     fn visit_document_mut(&mut self, document: &mut mandate::Document<'_>) {
+//!         document.blocks.push(Block::Heading {
+//!             level: 2,
+//!             content: vec![Inline::Text(Cow::Borrowed("ENVIRONMENT"))],
+//!         });
+//!         document.blocks.push(Block::Paragraph(vec![Inline::Text(Cow::Borrowed(
+//!             "NO_COLOR disables colored output.",
+//!         ))]));
+//!     }
+//! }
+//!
+//! let mut document = mandate::parse_markdown("## SYNOPSIS\n\ntool\n")?;
+//! Environment.visit_document_mut(&mut document);
+//! assert_eq!(document.blocks.len(), 4);
+//! # Ok::<(), mandate::MandateError>(())
+//! ```
+
+use crate::{Block, Document, Inline, ListItem};
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Read-only traversal over a document.
+pub trait Visitor {
+    /// Visit a whole document.
+    fn visit_document(&mut self, document: &Document<'_>) {
+        walk_document(self, document);
+    }
+
+    /// Visit a run of sibling blocks.
+    fn visit_blocks(&mut self, blocks: &[Block<'_>]) {
+        walk_blocks(self, blocks);
+    }
+
+    /// Visit a single block.
+    fn visit_block(&mut self, block: &Block<'_>) {
+        walk_block(self, block);
+    }
+
+    /// Visit one item of a list.
+    fn visit_list_item(&mut self, item: &ListItem<'_>) {
+        walk_list_item(self, item);
+    }
+
+    /// Visit a run of sibling inlines.
+    fn visit_inlines(&mut self, inlines: &[Inline<'_>]) {
+        walk_inlines(self, inlines);
+    }
+
+    /// Visit a single inline.
+    fn visit_inline(&mut self, inline: &Inline<'_>) {
+        walk_inline(self, inline);
+    }
+}
+
+/// Visit the blocks of `document`.
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document<'_>) {
+    visitor.visit_blocks(&document.blocks);
+}
+
+/// Visit each block in order.
+pub fn walk_blocks<V: Visitor + ?Sized>(visitor: &mut V, blocks: &[Block<'_>]) {
+    for block in blocks {
+        visitor.visit_block(block);
+    }
+}
+
+/// Visit the inlines, list items or nested blocks inside `block`.
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block<'_>) {
+    match block {
+        Block::Heading { content, .. } | Block::Paragraph(content) => {
+            visitor.visit_inlines(content);
+        }
+        Block::List { items, .. } => {
+            for item in items {
+                visitor.visit_list_item(item);
+            }
+        }
+        Block::FootnoteDefinition { blocks, .. } => visitor.visit_blocks(blocks),
+        Block::DefinitionList { items } => {
+            for item in items {
+                visitor.visit_inlines(&item.term);
+                visitor.visit_blocks(&item.blocks);
+            }
+        }
+        Block::CodeBlock { .. }
+        | Block::Rule
+        | Block::Math { .. }
+        | Block::Raw { .. }
+        | Block::Comment(_)
+        | Block::Directive { .. } => {}
+    }
+}
+
+/// Visit the blocks of a list item.
+pub fn walk_list_item<V: Visitor + ?Sized>(visitor: &mut V, item: &ListItem<'_>) {
+    visitor.visit_blocks(&item.blocks);
+}
+
+/// Visit each inline in order.
+pub fn walk_inlines<V: Visitor + ?Sized>(visitor: &mut V, inlines: &[Inline<'_>]) {
+    for inline in inlines {
+        visitor.visit_inline(inline);
+    }
+}
+
+/// Visit the children of styled text and links; other inlines are leaves.
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline<'_>) {
+    match inline {
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Superscript(children)
+        | Inline::Subscript(children)
+        | Inline::Link {
+            content: children, ..
+        } => visitor.visit_inlines(children),
+        Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::LineBreak(_)
+        | Inline::FootnoteReference(_)
+        | Inline::Math(_)
+        | Inline::Comment(_) => {}
+    }
+}
+
+/// Mutable traversal over a document.
+///
+/// The `Vec` hooks (`visit_blocks_mut`, `visit_inlines_mut`) exist so passes
+/// can insert, remove or splice nodes, not just edit them in place.
+pub trait VisitorMut {
+    /// Visit a whole document.
+    fn visit_document_mut(&mut self, document: &mut Document<'_>) {
+        walk_document_mut(self, document);
+    }
+
+    /// Visit a run of sibling blocks, which may be added to or removed.
+    fn visit_blocks_mut(&mut self, blocks: &mut Vec<Block<'_>>) {
+        walk_blocks_mut(self, blocks);
+    }
+
+    /// Visit a single block.
+    fn visit_block_mut(&mut self, block: &mut Block<'_>) {
+        walk_block_mut(self, block);
+    }
+
+    /// Visit one item of a list.
+    fn visit_list_item_mut(&mut self, item: &mut ListItem<'_>) {
+        walk_list_item_mut(self, item);
+    }
+
+    /// Visit a run of sibling inlines, which may be added to or removed.
+    fn visit_inlines_mut(&mut self, inlines: &mut Vec<Inline<'_>>) {
+        walk_inlines_mut(self, inlines);
+    }
+
+    /// Visit a single inline.
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'_>) {
+        walk_inline_mut(self, inline);
+    }
+}
+
+/// Visit the blocks of `document`.
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut Document<'_>) {
+    visitor.visit_blocks_mut(&mut document.blocks);
+}
+
+/// Visit each block in order.
+pub fn walk_blocks_mut<V: VisitorMut + ?Sized>(visitor: &mut V, blocks: &mut Vec<Block<'_>>) {
+    for block in blocks {
+        visitor.visit_block_mut(block);
+    }
+}
+
+/// Visit the inlines, list items or nested blocks inside `block`.
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block<'_>) {
+    match block {
+        Block::Heading { content, .. } | Block::Paragraph(content) => {
+            visitor.visit_inlines_mut(content);
+        }
+        Block::List { items, .. } => {
+            for item in items {
+                visitor.visit_list_item_mut(item);
+            }
+        }
+        Block::FootnoteDefinition { blocks, .. } => visitor.visit_blocks_mut(blocks),
+        Block::DefinitionList { items } => {
+            for item in items {
+                visitor.visit_inlines_mut(&mut item.term);
+                visitor.visit_blocks_mut(&mut item.blocks);
+            }
+        }
+        Block::CodeBlock { .. }
+        | Block::Rule
+        | Block::Math { .. }
+        | Block::Raw { .. }
+        | Block::Comment(_)
+        | Block::Directive { .. } => {}
+    }
+}
+
+/// Visit the blocks of a list item.
+pub fn walk_list_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut ListItem<'_>) {
+    visitor.visit_blocks_mut(&mut item.blocks);
+}
+
+/// Visit each inline in order.
+pub fn walk_inlines_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inlines: &mut Vec<Inline<'_>>) {
+    for inline in inlines {
+        visitor.visit_inline_mut(inline);
+    }
+}
+
+/// Visit the children of styled text and links; other inlines are leaves.
+pub fn walk_inline_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inline: &mut Inline<'_>) {
+    match inline {
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Superscript(children)
+        | Inline::Subscript(children)
+        | Inline::Link {
+            content: children, ..
+        } => visitor.visit_inlines_mut(children),
+        Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::LineBreak(_)
+        | Inline::FootnoteReference(_)
+        | Inline::Math(_)
+        | Inline::Comment(_) => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Uppercase the text of level-2 headings, which render as `.SH`.
+pub struct UppercaseSectionTitles;
+
+impl VisitorMut for UppercaseSectionTitles {
+    fn visit_block_mut(&mut self, block: &mut Block<'_>) {
+        match block {
+            Block::Heading { level: 2, content } => Uppercase.visit_inlines_mut(content),
+            _ => walk_block_mut(self, block),
+        }
+    }
+}
+
+struct Uppercase;
+
+impl VisitorMut for Uppercase {
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'_>) {
+        match inline {
+            Inline::Text(text) | Inline::Code(text) => {
+                if text.chars().any(char::is_lowercase) {
+                    *text = Cow::Owned(text.to_uppercase());
+                }
+            }
+            _ => walk_inline_mut(self, inline),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Replace links with their text content.
+pub struct StripLinks;
+
+impl VisitorMut for StripLinks {
+    fn visit_inlines_mut(&mut self, inlines: &mut Vec<Inline<'_>>) {
+        if inlines
+            .iter()
+            .any(|inline| matches!(inline, Inline::Link { .. }))
+        {
+            let mut flattened = Vec::with_capacity(inlines.len());
+            for inline in inlines.drain(..) {
+                match inline {
+                    Inline::Link { content, .. } => flattened.extend(content),
+                    other => flattened.push(other),
+                }
+            }
+            *inlines = flattened;
+        }
+        walk_inlines_mut(self, inlines);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Push headings down by `by` levels, capped at level 6.
+///
+/// The level-1 heading is left alone: it becomes the `NAME` section, and
+/// demoting it would lose that.
+pub struct DemoteHeadings {
+    pub by: u8,
+}
+
+impl Default for DemoteHeadings {
+    fn default() -> Self {
+        Self { by: 1 }
+    }
+}
+
+impl VisitorMut for DemoteHeadings {
+    fn visit_block_mut(&mut self, block: &mut Block<'_>) {
+        if let Block::Heading { level, .. } = block {
+            if *level > 1 {
+                *level = level.saturating_add(self.by).min(6);
+            }
+            return;
+        }
+        walk_block_mut(self, block);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Built-in passes, selectable by name (for example from the CLI).
+pub enum Pass {
+    /// [`UppercaseSectionTitles`], named `uppercase-titles`.
+    UppercaseSectionTitles,
+    /// [`StripLinks`], named `strip-links`.
+    StripLinks,
+    /// [`DemoteHeadings`] by one level, named `demote-headings`.
+    DemoteHeadings,
+}
+
+impl Pass {
+    /// Every built-in pass, in the order they are documented.
+    pub const ALL: [Pass; 3] = [
+        Pass::UppercaseSectionTitles,
+        Pass::StripLinks,
+        Pass::DemoteHeadings,
+    ];
+
+    /// The name used to select this pass.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::UppercaseSectionTitles => "uppercase-titles",
+            Pass::StripLinks => "strip-links",
+            Pass::DemoteHeadings => "demote-headings",
+        }
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Pass::ALL
+            .into_iter()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| {
+                let names = Pass::ALL.map(Pass::name).join(", ");
+                format!("unknown pass '{name}' (expected one of: {names})")
+            })
+    }
+}
+
+impl VisitorMut for Pass {
+    fn visit_document_mut(&mut self, document: &mut Document<'_>) {
+        match self {
+            Pass::UppercaseSectionTitles => UppercaseSectionTitles.visit_document_mut(document),
+            Pass::StripLinks => StripLinks.visit_document_mut(document),
+            Pass::DemoteHeadings => DemoteHeadings::default().visit_document_mut(document),
+        }
+    }
+}
+
+/// Run `passes` over `document`, in order.
+pub fn apply_passes(document: &mut Document<'_>, passes: &[Pass]) {
+    for mut pass in passes.iter().copied() {
+        pass.visit_document_mut(document);
+    }
+}
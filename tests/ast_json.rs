@@ -0,0 +1,72 @@
+#![cfg(feature = "serde")]
+
+use mandate::{ManpageOptions, document_from_json, document_to_json};
+
+fn options() -> ManpageOptions {
+    ManpageOptions::new("mandate", "1", "Test", None, None)
+}
+
+#[test]
+fn ast_json_round_trips_to_identical_roff() {
+    let markdown = r#"
+# mandate(1) -- Example Tool
+
+## OPTIONS
+
+- `--verbose`:
+
+Talk more.
+
+1. first
+2. **second** with [a link](https://example.com "title")
+
+```
+code
+```
+
+---
+
+- [x] done
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    let json = document_to_json(&document).expect("serialize");
+    let restored = document_from_json(&json).expect("deserialize");
+
+    let original = mandate::render_roff(&document, &options()).expect("render original");
+    let roundtrip = mandate::render_roff(&restored, &options()).expect("render restored");
+    assert_eq!(
+        original.lines().skip(1).collect::<Vec<_>>(),
+        roundtrip.lines().skip(1).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn ast_json_uses_snake_case_variants() {
+    let document = mandate::parse_markdown("## TITLE\n\nHello.\n").expect("parse");
+    let json = document_to_json(&document).expect("serialize");
+    assert!(json.contains("\"heading\""));
+    assert!(json.contains("\"paragraph\""));
+    assert!(json.contains("\"text\": \"Hello.\""));
+}
+
+#[test]
+fn ast_json_accepts_hand_written_documents() {
+    let json = r#"{"blocks": [
+        {"heading": {"level": 1, "content": [{"text": "tool(1) -- From JSON"}]}},
+        {"list": {"kind": {"ordered": {"start": 3}}, "items": [
+            {"blocks": [{"paragraph": [{"code": "--flag"}]}]}
+        ]}},
+        {"paragraph": [{"text": "a"}, {"line_break": "soft"}, {"text": "b"}]}
+    ]}"#;
+    let document = document_from_json(json).expect("deserialize");
+    let roff = mandate::render_roff(&document, &options()).expect("render");
+    assert!(roff.contains("\\fBtool\\fR \\- From JSON"));
+    assert!(roff.contains("\\fB\\-\\-flag\\fR"));
+    assert!(roff.contains("a b"));
+}
+
+#[test]
+fn ast_json_reports_malformed_input() {
+    let err = document_from_json("{\"blocks\": [{\"nope\": 1}]}").expect_err("should fail");
+    assert!(matches!(err, mandate::MandateError::Json(_)));
+}
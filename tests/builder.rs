@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_dir() -> PathBuf {
+    let mut path = std::env::temp_dir();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    path.push(format!("mandate-builder-{}-{}", std::process::id(), stamp));
+    path
+}
+
+#[test]
+fn builder_writes_yaml_manpage_into_directory() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = temp_dir();
+    let options = mandate::ManpageOptions::new("jq", "1", "jq Manual", None, None);
+
+    let written = mandate::Builder::new()
+        .input(manifest_dir.join("tests/fixtures/manual.yml"))
+        .options(options)
+        .validate(true)
+        .write_to(&out_dir)
+        .expect("builder should render");
+
+    assert_eq!(written, vec![out_dir.join("jq.1")]);
+    let roff = fs::read_to_string(&written[0]).expect("read output");
+    assert!(roff.starts_with(".TH \"jq\" \"1\""));
+    assert!(roff.contains(".SH \"SYNOPSIS\""));
+    let _ = fs::remove_dir_all(&out_dir);
+}
+
+#[test]
+fn builder_requires_input() {
+    let options = mandate::ManpageOptions::new("mandate", "1", "Test", None, None);
+    let err = mandate::Builder::new()
+        .options(options)
+        .write_to(temp_dir())
+        .expect_err("missing input should fail");
+    assert!(matches!(err, mandate::MandateError::Usage(_)));
+    assert_eq!(
+        err.to_string(),
+        "usage error: builder input path is not set"
+    );
+}
+
+#[test]
+fn builder_substitutes_the_crate_version() {
+    let dir = temp_dir();
+    let input = dir.join("tool.md");
+    fs::create_dir_all(&dir).expect("create temp dir");
+    fs::write(
+        &input,
+        "# tool(1) -- Does things\n\n## VERSION\n\n{{program}} {{version}}\n",
+    )
+    .expect("write input");
+    let options = mandate::ManpageOptions::new("tool", "1", "Tool Manual", None, None);
+
+    let written = mandate::Builder::new()
+        .input(&input)
+        .options(options)
+        .write_to(dir.join("out"))
+        .expect("builder should render");
+
+    let roff = fs::read_to_string(&written[0]).expect("read output");
+    assert!(roff.contains(&format!("tool {}\n", env!("CARGO_PKG_VERSION"))));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -118,3 +118,160 @@ fn cli_stdin_input_writes_output() {
     assert!(!fields[2].is_empty());
     assert_eq!(fields[4], "Test");
 }
+
+#[test]
+fn cli_stdin_front_matter_page_is_markdown() {
+    let mut child = Command::new(mandate_bin())
+        .args(["-i", "-", "-p", "mandate", "-t", "Test"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn mandate");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"---\ntitle: Example\n---\n# mandate(1) -- Example\n\n## DESCRIPTION\n\nRuns only when asked.\n")
+        .expect("write stdin");
+
+    let output = child.wait_with_output().expect("wait");
+    assert!(output.status.success());
+    let roff = String::from_utf8(output.stdout).expect("utf-8 output");
+    assert!(roff.contains(".SH \"DESCRIPTION\"\nRuns only when asked.\n"));
+}
+
+#[test]
+fn cli_pass_flag_transforms_document() {
+    let dir = temp_dir();
+    let input = dir.join("passes.md");
+    let output = dir.join("passes.1");
+
+    fs::write(
+        &input,
+        "# mandate(1) -- Example\n\n## Exit status\n\nZero.\n",
+    )
+    .expect("write input");
+
+    let status = Command::new(mandate_bin())
+        .args([
+            "-i",
+            input.to_str().unwrap(),
+            "-p",
+            "mandate",
+            "-t",
+            "Test",
+            "--pass",
+            "uppercase-titles",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .status()
+        .expect("run mandate");
+
+    assert!(status.success());
+    let roff = fs::read_to_string(output).expect("read output");
+    assert!(roff.contains(".SH \"EXIT STATUS\""));
+}
+
+#[test]
+fn cli_include_dir_extends_the_search_path() {
+    let dir = temp_dir();
+    let shared = dir.join("shared");
+    let input = dir.join("page.md");
+    let output = dir.join("page.1");
+
+    fs::create_dir_all(&shared).expect("create shared dir");
+    fs::write(shared.join("exit.md"), "## EXIT STATUS\n\nZero.\n").expect("write include");
+    fs::write(
+        &input,
+        "# mandate(1) -- Example\n\n<!-- include: exit.md -->\n",
+    )
+    .expect("write input");
+
+    let status = Command::new(mandate_bin())
+        .args([
+            "-i",
+            input.to_str().unwrap(),
+            "-p",
+            "mandate",
+            "-t",
+            "Test",
+            "-I",
+            shared.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .status()
+        .expect("run mandate");
+
+    assert!(status.success());
+    let roff = fs::read_to_string(output).expect("read output");
+    assert!(roff.contains(".SH \"EXIT STATUS\"\nZero.\n"));
+}
+
+#[test]
+fn cli_define_and_strict_variables() {
+    let dir = temp_dir();
+    let input = dir.join("vars.md");
+    let output = dir.join("vars.1");
+
+    fs::write(
+        &input,
+        "# mandate(1) -- Example\n\n## FILES\n\n{{config_dir}}/rc\n",
+    )
+    .expect("write input");
+    let run = |extra: &[&str]| {
+        Command::new(mandate_bin())
+            .args([
+                "-i",
+                input.to_str().unwrap(),
+                "-p",
+                "mandate",
+                "-t",
+                "Test",
+                "-o",
+                output.to_str().unwrap(),
+            ])
+            .args(extra)
+            .status()
+            .expect("run mandate")
+    };
+
+    assert!(run(&["-D", "config_dir=/etc/mandate"]).success());
+    let roff = fs::read_to_string(&output).expect("read output");
+    assert!(roff.contains("/etc/mandate/rc\n"));
+    assert!(!run(&["--strict-variables"]).success());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn cli_ast_json_round_trip() {
+    let dir = temp_dir();
+    let input = dir.join("ast.md");
+    let json = dir.join("ast.json");
+    let output = dir.join("ast.1");
+
+    fs::write(&input, "# mandate(1) -- Example\n\nParagraph.\n").expect("write input");
+
+    let status = Command::new(mandate_bin())
+        .args(["-i", input.to_str().unwrap(), "-p", "mandate", "-t", "Test"])
+        .args(["--format", "ast-json", "-o", json.to_str().unwrap()])
+        .status()
+        .expect("run mandate");
+    assert!(status.success());
+    assert!(
+        fs::read_to_string(&json)
+            .expect("read json")
+            .contains("\"paragraph\"")
+    );
+
+    let status = Command::new(mandate_bin())
+        .args(["-i", json.to_str().unwrap(), "-p", "mandate", "-t", "Test"])
+        .args(["--from", "ast-json", "-o", output.to_str().unwrap()])
+        .status()
+        .expect("run mandate");
+    assert!(status.success());
+    let roff = fs::read_to_string(output).expect("read output");
+    assert!(roff.contains("\\fBmandate\\fR \\- Example"));
+    assert!(roff.contains("Paragraph"));
+}
@@ -0,0 +1,59 @@
+use mandate::{Document, ManpageOptions};
+
+#[test]
+fn builder_matches_parsed_markdown() {
+    let markdown = r#"# mytool -- does things
+
+## SYNOPSIS
+
+mytool --verbose FILE
+
+## OPTIONS
+
+- `-v, --verbose`:
+
+Talk more.
+
+### Advanced
+
+- one
+- two
+
+```console
+$ mytool -v
+```
+
+## TROUBLESHOOTING
+
+- [x] plugged in
+- [ ] turned on
+
+---
+"#;
+    let parsed = mandate::parse_markdown(markdown).expect("parse");
+    let built = Document::builder()
+        .name("mytool", "does things")
+        .section("SYNOPSIS", |s| s.para("mytool --verbose FILE"))
+        .section("OPTIONS", |s| {
+            s.option("-v, --verbose", "Talk more.")
+                .subsection("Advanced", |s| {
+                    s.bullets(["one", "two"])
+                        .code_lang("console", "$ mytool -v")
+                })
+        })
+        .section("TROUBLESHOOTING", |s| {
+            s.tasks([(true, "plugged in"), (false, "turned on")]).rule()
+        })
+        .build();
+    assert_eq!(built, parsed);
+}
+
+#[test]
+fn builder_option_renders_as_tagged_paragraph() {
+    let document = Document::builder()
+        .section("OPTIONS", |s| s.option("--seq", "Use json-seq."))
+        .build();
+    let options = ManpageOptions::new("mytool", "1", "Test", None, None);
+    let roff = mandate::render_roff(&document, &options).expect("render");
+    assert!(roff.contains(".TP\n\\fB\\-\\-seq\\fR:\n.IP\nUse json-seq.\n"));
+}
@@ -0,0 +1,28 @@
+//! Golden tests: each `tests/golden/<name>.md` renders to `<name>.1`.
+//!
+//! The expected files hold everything after the `.TH` line, which carries
+//! the date and would make the goldens expire daily.
+
+use mandate::{ManpageOptions, convert_markdown_to_roff};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn markdown_matches_golden_roff() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut checked = 0;
+    for entry in fs::read_dir(&dir).expect("read golden dir") {
+        let path = entry.expect("golden entry").path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+        let markdown = fs::read_to_string(&path).expect("read markdown");
+        let expected = fs::read_to_string(path.with_extension("1")).expect("read golden");
+        let options = ManpageOptions::new("tool", "1", "Tool Manual", None, None);
+        let roff = convert_markdown_to_roff(&markdown, &options).expect("render roff");
+        let (_, body) = roff.split_once('\n').expect("TH line");
+        assert_eq!(body, expected, "{} drifted from its golden", path.display());
+        checked += 1;
+    }
+    assert!(checked > 0, "no golden inputs found");
+}
@@ -0,0 +1,116 @@
+use mandate::{InputKind, MandateError, SourceOptions, resolve_includes};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/includes")
+        .join(name)
+}
+
+fn resolve(name: &str, options: &SourceOptions) -> mandate::Result<mandate::ResolvedSource> {
+    let path = fixture(name);
+    let source = fs::read_to_string(&path).expect("read fixture");
+    let kind = mandate::input_kind(&path.to_string_lossy());
+    resolve_includes(&source, Some(&path), kind, options)
+}
+
+#[test]
+fn markdown_includes_nest_and_skip_code_fences() {
+    let resolved = resolve("page.md", &SourceOptions::new()).expect("resolve");
+    assert!(resolved.text.contains(
+        "Does things.\n\n## EXIT STATUS\n\nZero on success, one when things go sideways.\n"
+    ));
+    assert!(
+        resolved
+            .text
+            .contains("```md\n<!-- include: not-expanded.md -->\n```\n")
+    );
+    assert_eq!(
+        resolved.dependencies,
+        vec![fixture("shared/exit-status.md"), fixture("shared/codes.md")]
+    );
+}
+
+#[test]
+fn yaml_includes_read_text_and_splice_sections() {
+    let resolved = resolve("page.yml", &SourceOptions::new()).expect("resolve");
+    let document = mandate::parse_yaml_to_document(&resolved.text).expect("parse");
+    let roff = mandate::render_roff(
+        &document,
+        &mandate::ManpageOptions::new("tool", "1", "Tool", None, None),
+    )
+    .expect("render");
+    assert!(roff.contains(
+        "Common body text.\n.P\nZero on success, one when things go sideways.\n.SH \"DESCRIPTION\""
+    ));
+    assert!(roff.contains(".SH \"BUGS\"\nPlenty.\n.SH \"AUTHORS\"\nSeveral.\n"));
+    assert_eq!(resolved.dependencies.len(), 3);
+}
+
+#[test]
+fn include_key_merges_into_a_mapping() {
+    let source = "include: shared/meta.yml\nheadline: local\n";
+    let dir = fixture("");
+    let resolved = resolve_includes(
+        source,
+        Some(&dir.join("inline.yml")),
+        InputKind::Yaml,
+        &SourceOptions::new(),
+    )
+    .expect("resolve");
+    assert!(resolved.text.contains("headline: local"));
+    assert!(!resolved.text.contains("headline: shared"));
+    assert!(resolved.text.contains("manpage_epilogue: shared"));
+}
+
+#[test]
+fn cycles_and_missing_files_point_at_the_including_line() {
+    let err = resolve("cycle.md", &SourceOptions::new()).expect_err("cycle");
+    let MandateError::Include(message) = err else {
+        panic!("expected an include error, got {err:?}");
+    };
+    assert!(message.starts_with(&format!(
+        "{}:1: include '../cycle.md': include cycle: ",
+        fixture("shared/cycle.md").display()
+    )));
+    let cycle = fixture("cycle.md").canonicalize().expect("canonical");
+    assert!(message.ends_with(&format!(" -> {}", cycle.display())));
+
+    let err = resolve_includes(
+        "## A\n\n<!-- include: nowhere.md -->\n",
+        None,
+        InputKind::Markdown,
+        &SourceOptions::new(),
+    )
+    .expect_err("missing");
+    assert_eq!(
+        err.to_string(),
+        "include error: <stdin>:3: include 'nowhere.md': file not found"
+    );
+}
+
+#[test]
+fn search_path_is_tried_after_the_including_directory() {
+    let options = SourceOptions::new().with_search_dir(fixture("shared"));
+    let resolved = resolve_includes(
+        "## A\n\n<!-- include: codes.md -->\n",
+        None,
+        InputKind::Auto,
+        &options,
+    )
+    .expect("resolve");
+    assert_eq!(
+        resolved.text,
+        "## A\n\nZero on success, one when things go sideways.\n"
+    );
+    assert_eq!(resolved.dependencies, vec![fixture("shared/codes.md")]);
+}
+
+#[test]
+fn front_matter_pages_on_stdin_stay_markdown() {
+    let markdown = "---\ntitle: Example\n---\n# tool(1) -- Example\n\nCan include other files.\n";
+    let resolved =
+        resolve_includes(markdown, None, InputKind::Auto, &SourceOptions::new()).expect("resolve");
+    assert_eq!(resolved.text, markdown);
+}
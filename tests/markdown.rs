@@ -54,6 +54,323 @@ Paragraph after foo.
     assert!(roff.contains(".TP"));
     assert!(roff.contains("Foo:"));
     assert!(roff.contains(".IP"));
-    assert!(roff.contains("Paragraph after foo\\."));
+    assert!(roff.contains("Paragraph after foo.\n"));
     assert!(roff.contains("Foo:\n.IP"));
 }
+
+#[test]
+fn sentence_per_line_breaks_after_sentences_only() {
+    let markdown = r#"
+## DESCRIPTION
+
+Reads input, e.g. a file. Writes **output.** Then
+exits `0`. Done? So do I. Then go.
+"#;
+    let options = options().with_sentence_per_line(true);
+    let roff = convert_markdown_to_roff(markdown, &options).expect("render roff");
+    assert!(roff.contains(
+        "\nReads input, e.g.\\& a file.\nWrites \\fBoutput.\\fR\nThen exits \\fB0\\fR.\nDone?\nSo do I.\nThen go.\n"
+    ));
+}
+
+#[test]
+fn task_lists_and_rules_survive() {
+    let markdown = r#"
+## TROUBLESHOOTING
+
+- [x] Check the cable
+- [ ] Reboot
+
+---
+
+Still broken?
+"#;
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(".IP \"[x]\" 4\nCheck the cable\n.IP \"[ ]\" 4\nReboot\n"));
+    assert!(roff.contains(".sp\n.ce 1\n\\l'20n'\n.P\nStill broken?\n"));
+
+    let roff =
+        convert_markdown_to_roff("## CHECKS\n\n- [ ] Verify:\n\nThe signature.\n", &options())
+            .expect("render roff");
+    assert!(roff.contains(".TP\n[ ] Verify:\n.IP\nThe signature.\n"));
+}
+
+#[test]
+fn strikethrough_and_scripts_render_instead_of_failing() {
+    let markdown = "## NOTES\n\nThe ~~--old~~ flag costs 2 ^10^ bytes, see ~1~.\n";
+    let roff =
+        convert_markdown_to_roff(markdown, &options().with_scripts(true)).expect("render roff");
+    assert!(roff.contains(
+        "The \\fIdeprecated:\\fR \\-\\-old flag costs 2 \\u\\s-210\\s+2\\d bytes, see \\d\\s-21\\s+2\\u.\n"
+    ));
+
+    let options = options().with_strikethrough(mandate::StrikethroughStyle::Overstrike);
+    let roff = convert_markdown_to_roff("~~a b~~", &options).expect("render roff");
+    assert!(roff.contains("\\o'a\\(hy' \\o'b\\(hy'"));
+}
+
+#[test]
+fn carets_and_tildes_in_prose_are_kept_without_scripts() {
+    let markdown = "## NOTES\n\nRegex ^foo matches, so does bar^ too. See ~/a and ~/b.\n";
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(
+        roff.contains("Regex \\(hafoo matches, so does bar\\(ha too. See \\(ti/a and \\(ti/b.\n")
+    );
+
+    let markdown = "## FILES\n\nReads ~/.config/x~ and ~/.x~, not ~~/etc/x~~.\n";
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(
+        "Reads \\(ti/.config/x\\(ti and \\(ti/.x\\(ti, not \\fIdeprecated:\\fR /etc/x.\n"
+    ));
+}
+
+#[test]
+fn footnotes_are_numbered_and_collected_into_notes() {
+    let markdown = r#"
+## DESCRIPTION
+
+Follows the spec[^spec], see [the site](https://example.com)[^spec].
+
+[^spec]: The full specification.
+"#;
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains("Follows the spec[1], see the site[1].\n"));
+    assert!(roff.ends_with(".SH \"NOTES\"\n.IP \" 1.\" 4\nThe full specification.\n"));
+
+    let options = options().with_link_notes(true);
+    let roff = convert_markdown_to_roff(markdown, &options).expect("render roff");
+    assert!(roff.contains("see the site[2][1].\n"));
+    assert!(roff.contains(".IP \" 2.\" 4\nthe site\n.RS 4\n\\%https://example.com\n.RE\n"));
+}
+
+#[test]
+fn footnotes_join_an_existing_notes_section() {
+    let markdown = "## NOTES\n\nSee below[^a].\n\n[^a]: Below.\n";
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert_eq!(roff.matches(".SH \"NOTES\"").count(), 1);
+    assert!(roff.ends_with("See below[1].\n.IP \" 1.\" 4\nBelow.\n"));
+}
+
+#[test]
+fn math_renders_as_text_or_eqn() {
+    let markdown = r#"
+## DESCRIPTION
+
+Costs $O(n^2)$ for $\alpha \le \frac{1}{2}$, or $5.
+$$
+\sum_{i=1}^{n} x_i
+$$
+"#;
+    let text = options().with_math(mandate::MathMode::Text);
+    let roff = convert_markdown_to_roff(markdown, &text).expect("render roff");
+    assert!(roff.contains("Costs \\fIO(n\\[u00B2])\\fR for \\fI\\[u03B1] \\(<= 1/2\\fR, or $5.\n"));
+    assert!(roff.contains(".RS 4\n\\fI\\[u2211]_(i=1)\\(han x_i\\fR\n.RE\n"));
+
+    let options = options().with_math(mandate::MathMode::Eqn);
+    let roff = convert_markdown_to_roff(markdown, &options).expect("render roff");
+    assert!(roff.starts_with("'\\\" e\n.TH "));
+    assert!(roff.contains(".EQ\ndelim $$\n.EN\n"));
+    assert!(roff.contains("Costs $O(n sup {2} )$ for $alpha <= {1} over {2}$, or \\(Do5.\n"));
+    assert!(roff.contains(".EQ\nsum sub {i=1} sup {n} x sub {i}\n.EN\n"));
+}
+
+#[test]
+fn dollar_signs_in_prose_are_kept_without_math() {
+    let markdown = "## ENVIRONMENT\n\nSet $HOME/$USER and cost $5 or $10.\n";
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains("Set $HOME/$USER and cost $5 or $10.\n"));
+}
+
+#[test]
+fn code_blocks_are_styled_by_language() {
+    let markdown = r#"
+## EXAMPLES
+
+```console
+$ mytool --list
+one two
+```
+
+```sh title="loop"
+for f in *.md; do echo "done # not a comment" # but this is
+done
+```
+
+```text
+if then
+```
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    assert!(matches!(
+        &document.blocks[1],
+        mandate::Block::CodeBlock { lang: Some(lang), .. } if lang == "console"
+    ));
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(".EX\n\\fB$ mytool \\-\\-list\\fR\none two\n.EE\n"));
+    assert!(roff.contains(
+        "\\fBfor\\fR f \\fBin\\fR *.md; \\fBdo\\fR echo \"done # not a comment\" \\fI# but this is\\fR\n\\fBdone\\fR\n"
+    ));
+    assert!(roff.contains(".EX\nif then\n.EE\n"));
+}
+
+#[test]
+fn code_blocks_nest_in_list_items_and_keep_their_layout() {
+    let markdown = r#"
+## EXAMPLES
+
+- Install:
+
+  ```text
+  make	install
+
+  make check
+  ```
+
+  Then run it.
+- Done
+
+```text
+one
+```
+```text
+two
+```
+"#;
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert_eq!(roff.matches(".de EX").count(), 1);
+    assert!(roff.contains(
+        ".RS 4\n.EX\nmake    install\n\\&\nmake check\n.EE\n.RE\n.IP\nThen run it.\n.IP \"\\(bu\" 4\n"
+    ));
+    assert!(roff.contains(".EX\none\n\\&\ntwo\n.EE\n"));
+
+    let options = options().with_tab_width(2).with_merge_code_blocks(false);
+    let roff = convert_markdown_to_roff(markdown, &options).expect("render roff");
+    assert!(roff.contains("make  install\n"));
+    assert!(roff.contains(".EX\none\n.EE\n.RE\n.RS 4\n.EX\ntwo\n.EE\n"));
+}
+
+#[test]
+fn roff_passthrough_is_written_verbatim() {
+    let markdown = r#"
+## FILES
+
+```roff
+.TS
+l l.
+a\-b	c
+.TE
+```
+
+<!-- roff: .ne 5 -->
+
+Next.
+
+<!-- roff:
+.nf
+  indented
+
+.fi
+-->
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    assert!(matches!(
+        &document.blocks[2],
+        mandate::Block::Raw { format, text } if format == "roff" && text == ".ne 5\n"
+    ));
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(".SH \"FILES\"\n.TS\nl l.\na\\-b\tc\n.TE\n.ne 5\n.P\nNext.\n"));
+    assert!(roff.ends_with("Next.\n.nf\n  indented\n\n.fi\n"));
+}
+
+#[test]
+fn html_comments_are_dropped_or_kept_as_roff_comments() {
+    let markdown = r#"
+## DESCRIPTION
+
+<!-- TODO: document --frob -->
+
+Reads <!-- quietly --> input.
+
+<!-- mandate: toc depth=2 -->
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    assert!(matches!(
+        &document.blocks[3],
+        mandate::Block::Directive { name, args } if name == "toc" && args == "depth=2"
+    ));
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(".SH \"DESCRIPTION\"\nReads input.\n"));
+    assert!(!roff.contains("frob"));
+
+    let options = options().with_comments(mandate::CommentMode::Roff);
+    let roff = convert_markdown_to_roff(markdown, &options).expect("render roff");
+    assert!(roff.contains(
+        ".SH \"DESCRIPTION\"\n.\\\" TODO: document --frob\nReads input.\n.\\\" quietly\n"
+    ));
+    assert!(!roff.contains("toc"));
+}
+
+#[test]
+fn safe_inline_html_subset_is_interpreted() {
+    let markdown = r#"
+## KEYS
+
+Press <kbd>Ctrl</kbd>+<kbd>C</kbd>, pass <var>file</var> or <code>--all</code>.<br>
+Then let go.
+
+<dl>
+  <dt><code>--frob</code></dt>
+  <dd>Frobnicate <var>n</var> times.</dd>
+  <dd>Twice, if asked.</dd>
+</dl>
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    assert!(matches!(
+        &document.blocks[2],
+        mandate::Block::DefinitionList { items } if items.len() == 1 && items[0].blocks.len() == 2
+    ));
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(
+        "Press \\fBCtrl\\fR+\\fBC\\fR, pass \\fIfile\\fR or \\fB\\-\\-all\\fR.\n.br\nThen let go.\n"
+    ));
+    assert!(
+        roff.contains(
+            ".TP\n\\fB\\-\\-frob\\fR\nFrobnicate \\fIn\\fR times.\n.IP\nTwice, if asked.\n"
+        )
+    );
+
+    let roff =
+        convert_markdown_to_roff("- one<br>\n  two <b>open\n", &options()).expect("render roff");
+    assert!(roff.contains("one\ntwo \\fIb\\fRopen\n"));
+
+    let markdown = "## A\n\n<dl>\n<dt></dt>\n<dd>No term.</dd>\n</dl>\n";
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(".TP\nNo term.\n"));
+}
+
+#[test]
+fn headings_below_subsections_indent_what_follows() {
+    let markdown = r#"
+## FILTERS
+
+### Paths
+
+#### getpath
+
+Gets a path.
+
+##### Errors
+
+Raises.
+
+#### paths
+
+Lists paths.
+
+## BUGS
+"#;
+    let roff = convert_markdown_to_roff(markdown, &options()).expect("render roff");
+    assert!(roff.contains(
+        ".SS \"Paths\"\n.P\n\\fBgetpath\\fR\n.RS 4\nGets a path.\n.P\n\\fBErrors\\fR\n.RS 4\nRaises.\n.RE\n.RE\n.P\n\\fBpaths\\fR\n.RS 4\nLists paths.\n.RE\n.SH \"BUGS\"\n"
+    ));
+}
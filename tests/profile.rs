@@ -0,0 +1,90 @@
+use mandate::{InputKind, ManpageOptions, apply_profile};
+
+fn options() -> ManpageOptions {
+    ManpageOptions::new("tool", "1", "Tool Manual", None, None)
+}
+
+const MARKDOWN: &str = r#"## FILES
+
+<!-- if platform=linux -->
+~/.config/tool/rc
+<!-- if build=plugin -->
+~/.config/host/plugins/tool.toml
+<!-- endif -->
+<!-- else -->
+~/Library/Application Support/tool/rc
+<!-- endif -->
+
+```md
+<!-- if windows -->
+```
+"#;
+
+#[test]
+fn markdown_branches_follow_the_profile() {
+    let linux = options().with_profile("platform", "linux");
+    let text = apply_profile(MARKDOWN, InputKind::Markdown, &linux).expect("apply");
+    assert_eq!(
+        text,
+        "## FILES\n\n~/.config/tool/rc\n\n```md\n<!-- if windows -->\n```\n"
+    );
+
+    let plugin = linux.with_profile("build", "plugin");
+    let text = apply_profile(MARKDOWN, InputKind::Markdown, &plugin).expect("apply");
+    assert!(text.contains("~/.config/tool/rc\n~/.config/host/plugins/tool.toml\n\n```"));
+
+    let text = apply_profile(MARKDOWN, InputKind::Markdown, &options()).expect("apply");
+    assert!(text.starts_with("## FILES\n\n~/Library/Application Support/tool/rc\n\n```"));
+}
+
+#[test]
+fn unbalanced_conditionals_are_errors() {
+    let err = apply_profile("a\n<!-- if linux -->\nb\n", InputKind::Markdown, &options())
+        .expect_err("unclosed");
+    assert_eq!(
+        err.to_string(),
+        "markdown parse error: line 2: <!-- if --> without <!-- endif -->"
+    );
+    let err = apply_profile("<!-- endif -->\n", InputKind::Markdown, &options())
+        .expect_err("stray endif");
+    assert!(
+        err.to_string()
+            .ends_with("line 1: <!-- endif --> without <!-- if -->")
+    );
+}
+
+#[test]
+fn yaml_only_keys_select_sections_and_entries() {
+    let yaml = r#"headline: tool
+meta:
+  only: Linux only
+manpage_intro: |
+  # tool(1) -- Does things
+body: |
+  Runs on {{only}}.
+manpage_epilogue: ""
+sections:
+  - title: Files
+    body: Config files.
+    entries:
+      - title: plugin.toml
+        body: Plugin config.
+        only: [build=plugin]
+      - title: tool.toml
+        body: Standalone config.
+        only: build!=plugin
+  - title: Environment
+    only: [macos, windows]
+    body: Read at startup.
+"#;
+    mandate::validate_yaml_with_schema_str(yaml, mandate::BUILTIN_SCHEMA).expect("only: is valid");
+    let options = options().with_profile("platform", "macos");
+    let text = apply_profile(yaml, InputKind::Yaml, &options).expect("apply");
+    mandate::validate_yaml_with_schema_str(&text, mandate::BUILTIN_SCHEMA).expect("valid");
+    let text = mandate::substitute_variables(&text, InputKind::Yaml, &options).expect("substitute");
+    let roff = mandate::convert_yaml_to_roff(&text, &options).expect("render");
+    assert!(!roff.contains("plugin.toml"));
+    assert!(roff.contains(".SS \"tool.toml\"\nStandalone config.\n"));
+    assert!(roff.contains(".SH \"ENVIRONMENT\"\nRead at startup.\n"));
+    assert!(roff.contains("Runs on Linux only.\n"));
+}
@@ -0,0 +1,113 @@
+use mandate::{Inline, ListItem, ListKind, ManpageOptions, Renderer, Result};
+
+/// Renders a document as indented plain text, one node per line.
+#[derive(Default)]
+struct Plain {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Plain {
+    fn push(&mut self, line: String) {
+        self.lines
+            .push(format!("{}{line}", "  ".repeat(self.depth)));
+    }
+}
+
+fn text(inlines: &[Inline<'_>]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) | Inline::Code(text) | Inline::Math(text) => out.push_str(text),
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Superscript(children)
+            | Inline::Subscript(children) => out.push_str(&text(children)),
+            Inline::Link { content, .. } => out.push_str(&text(content)),
+            Inline::LineBreak(_) => out.push(' '),
+            Inline::FootnoteReference(label) => out.push_str(&format!("[^{label}]")),
+            Inline::Comment(_) => {}
+        }
+    }
+    out
+}
+
+impl Renderer for Plain {
+    fn start(&mut self, options: &ManpageOptions) -> Result<()> {
+        self.push(format!("start {}", options.program));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.push("finish".to_string());
+        Ok(())
+    }
+
+    fn heading(&mut self, level: u8, content: &[Inline<'_>]) -> Result<()> {
+        self.push(format!("h{level} {}", text(content)));
+        Ok(())
+    }
+
+    fn paragraph(&mut self, content: &[Inline<'_>]) -> Result<()> {
+        self.push(format!("p {}", text(content)));
+        Ok(())
+    }
+
+    fn list(&mut self, kind: &ListKind, items: &[ListItem<'_>]) -> Result<()> {
+        self.push(format!("list {}", matches!(kind, ListKind::Ordered { .. })));
+        self.depth += 1;
+        for item in items {
+            self.list_item(item)?;
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn code_block(&mut self, _lang: Option<&str>, text: &str) -> Result<()> {
+        self.push(format!("code {}", text.trim_end()));
+        Ok(())
+    }
+
+    fn inline(&mut self, _inline: &Inline<'_>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_renderer_visits_every_block() {
+    let markdown = r#"
+# mandate(1) -- Example
+
+## OPTIONS
+
+1. first *item*
+2. second
+
+```
+code
+```
+
+```roff
+.ne 5
+```
+"#;
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    let options = ManpageOptions::new("mandate", "1", "Test", None, None);
+    let mut plain = Plain::default();
+    // The roff passthrough block is none of this renderer's business.
+    mandate::render(&document, &options, &mut plain).expect("render");
+    assert_eq!(
+        plain.lines,
+        [
+            "start mandate",
+            "h1 mandate(1) -- Example",
+            "h2 OPTIONS",
+            "list true",
+            "  p first item",
+            "  p second",
+            "code code",
+            "finish",
+        ]
+    );
+}
@@ -0,0 +1,84 @@
+use mandate::{InputKind, MandateError, ManpageOptions, substitute_variables};
+
+fn options() -> ManpageOptions {
+    ManpageOptions::new("tool", "1", "Tool Manual", None, None)
+}
+
+#[test]
+fn markdown_takes_values_from_options_front_matter_and_environment() {
+    let markdown = r#"---
+config_dir: ~/.config/tool
+version: 1.0
+---
+# {{program}}({{ section }}) -- {{title}}
+
+Reads {{config_dir}}/rc, version {{version}}, from {{env.CARGO_MANIFEST_DIR}}.
+"#;
+    let options = options().with_variable("version", "2.1");
+    let text = substitute_variables(markdown, InputKind::Markdown, &options).expect("substitute");
+    assert_eq!(
+        text,
+        format!(
+            "# tool(1) -- Tool Manual\n\nReads ~/.config/tool/rc, version 2.1, from {}.\n",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    );
+}
+
+#[test]
+fn unused_front_matter_is_still_consumed() {
+    let markdown = "---\ntitle: Tool\n---\n# tool(1) -- Example\n";
+    let text = substitute_variables(markdown, InputKind::Markdown, &options()).expect("substitute");
+    assert_eq!(text, "# tool(1) -- Example\n");
+}
+
+#[test]
+fn escapes_foreign_templates_and_unknown_names_survive() {
+    let markdown = "Write \\{{program}} for {{program}}; {{#each}} and {{nope}} stay.\n";
+    let text = substitute_variables(markdown, InputKind::Auto, &options()).expect("substitute");
+    assert_eq!(
+        text,
+        "Write {{program}} for tool; {{#each}} and {{nope}} stay.\n"
+    );
+
+    let err = substitute_variables(
+        markdown,
+        InputKind::Auto,
+        &options().with_strict_variables(true),
+    )
+    .expect_err("strict");
+    assert!(matches!(err, MandateError::Variable(_)));
+    assert_eq!(
+        err.to_string(),
+        "variable error: undefined variable 'nope' in \"Write \\{{program}} for {{program}}; {{#each}} and {{nope}} stay.\""
+    );
+}
+
+#[test]
+fn code_fences_are_left_as_written() {
+    let markdown = "Built by {{program}}:\n\n```go\n{{ range .Items }}{{date}}{{ end }}\n```\n\nSee {{program}}.\n";
+    let options = options().with_strict_variables(true);
+    let text = substitute_variables(markdown, InputKind::Markdown, &options).expect("substitute");
+    assert_eq!(
+        text,
+        "Built by tool:\n\n```go\n{{ range .Items }}{{date}}{{ end }}\n```\n\nSee tool.\n"
+    );
+}
+
+#[test]
+fn yaml_values_are_substituted_without_breaking_the_document() {
+    let yaml = r#"headline: "{{program}}"
+meta:
+  usage: "{{program}}: [options]"
+manpage_intro: |
+  # {{program}}(1) -- Does things
+body: |
+  Usage: {{usage}}
+manpage_epilogue: ""
+sections: []
+"#;
+    let text = substitute_variables(yaml, InputKind::Yaml, &options()).expect("substitute");
+    let roff = mandate::convert_yaml_to_roff(&text, &options()).expect("render");
+    assert!(roff.contains("\\fBtool\\fR \\- Does things\n"));
+    assert!(roff.contains("Usage: tool: [options]\n"));
+}
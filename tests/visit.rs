@@ -0,0 +1,93 @@
+use mandate::visit::{
+    self, DemoteHeadings, Pass, StripLinks, UppercaseSectionTitles, Visitor, VisitorMut,
+};
+use mandate::{Block, Inline, ManpageOptions};
+
+fn options() -> ManpageOptions {
+    ManpageOptions::new("mandate", "1", "Test", None, None)
+}
+
+#[derive(Default)]
+struct CountLinks(usize);
+
+impl Visitor for CountLinks {
+    fn visit_inline(&mut self, inline: &Inline<'_>) {
+        if matches!(inline, Inline::Link { .. }) {
+            self.0 += 1;
+        }
+        visit::walk_inline(self, inline);
+    }
+}
+
+#[test]
+fn visitor_reaches_nested_inlines() {
+    let markdown = "See [one](a).\n\n- *[two](b)*\n- plain\n";
+    let document = mandate::parse_markdown(markdown).expect("parse");
+    let mut counter = CountLinks::default();
+    counter.visit_document(&document);
+    assert_eq!(counter.0, 2);
+}
+
+#[test]
+fn uppercase_section_titles_only_touches_sh_headings() {
+    let markdown = "# tool(1) -- does things\n\n## Exit status\n\n### Details\n";
+    let mut document = mandate::parse_markdown(markdown).expect("parse");
+    UppercaseSectionTitles.visit_document_mut(&mut document);
+    let roff = mandate::render_roff(&document, &options()).expect("render");
+    assert!(roff.contains(".SH \"EXIT STATUS\""));
+    assert!(roff.contains(".SS \"Details\""));
+    assert!(roff.contains("does things"));
+}
+
+#[test]
+fn strip_links_keeps_link_text() {
+    let mut document =
+        mandate::parse_markdown("See [the *docs*](https://example.com).\n").expect("parse");
+    StripLinks.visit_document_mut(&mut document);
+    match &document.blocks[0] {
+        Block::Paragraph(inlines) => {
+            assert!(
+                !inlines
+                    .iter()
+                    .any(|inline| matches!(inline, Inline::Link { .. }))
+            );
+            assert!(
+                inlines
+                    .iter()
+                    .any(|inline| matches!(inline, Inline::Emphasis(_)))
+            );
+        }
+        other => panic!("unexpected block: {other:?}"),
+    }
+}
+
+#[test]
+fn demote_headings_keeps_name_heading() {
+    let markdown = "# tool(1) -- x\n\n## A\n\n###### F\n";
+    let mut document = mandate::parse_markdown(markdown).expect("parse");
+    DemoteHeadings { by: 2 }.visit_document_mut(&mut document);
+    let levels = document
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Heading { level, .. } => Some(*level),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(levels, [1, 4, 6]);
+}
+
+#[test]
+fn passes_parse_by_name_and_compose() {
+    let passes = ["strip-links", "uppercase-titles"]
+        .iter()
+        .map(|name| name.parse::<Pass>().expect("known pass"))
+        .collect::<Vec<_>>();
+    assert_eq!(passes, [Pass::StripLinks, Pass::UppercaseSectionTitles]);
+    assert!("nope".parse::<Pass>().unwrap_err().contains("strip-links"));
+
+    let mut document = mandate::parse_markdown("## See [also](x)\n").expect("parse");
+    visit::apply_passes(&mut document, &passes);
+    let roff = mandate::render_roff(&document, &options()).expect("render");
+    assert!(roff.contains(".SH \"SEE ALSO\""));
+}
@@ -11,3 +11,124 @@ fn converts_yaml_manual_to_markdown() {
     assert!(markdown.contains("=> "));
     assert!(markdown.contains("jq '"));
 }
+
+const VERSIONED: &str = r#"headline: jq
+manpage_intro: |
+  # jq(1) -- Command-line JSON processor
+body: ""
+manpage_epilogue: ""
+sections:
+  - title: Builtins
+    body: Some builtins.
+    entries:
+      - title: ltrimstr
+        since: 1.5
+        body: Trims.
+      - title: getpath
+        since: "1.10"
+        body: Gets.
+      - title: leaf_paths
+        deprecated: 1.6
+        removed: 1.8
+        body: Old.
+      - title: splits
+        since: 1.7-rc1
+        body: Splits.
+      - title: tojson
+        since: 1.10rc1
+        body: Encodes.
+  - title: Modules
+    since: 1.5
+    body: Modules.
+"#;
+
+#[test]
+fn version_keys_render_as_an_annotation_line() {
+    mandate::validate_yaml_with_schema_str(VERSIONED, mandate::BUILTIN_SCHEMA).expect("valid");
+    let markdown = convert_yaml_to_markdown(VERSIONED).expect("convert yaml");
+    assert!(markdown.contains("### ltrimstr\n\n*Added in 1.5.*\n\nTrims."));
+    assert!(markdown.contains("### leaf_paths\n\n*Deprecated in 1.6, removed in 1.8.*\n\nOld."));
+    assert!(markdown.contains("## MODULES\n\n*Added in 1.5.*\n\nModules."));
+}
+
+#[test]
+fn target_version_filters_entries_and_sections() {
+    let titles = |version: &str| {
+        let options = mandate::ManpageOptions::new("jq", "1", "jq Manual", None, None)
+            .with_target_version(version);
+        let yaml = mandate::apply_profile(VERSIONED, mandate::InputKind::Yaml, &options)
+            .expect("apply profile");
+        let markdown = convert_yaml_to_markdown(&yaml).expect("convert yaml");
+        markdown
+            .lines()
+            .filter(|line| line.starts_with("##"))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(titles("1.4"), ["## BUILTINS", "### leaf_paths"]);
+    assert_eq!(
+        titles("1.7-beta"),
+        [
+            "## BUILTINS",
+            "### ltrimstr",
+            "### leaf_paths",
+            "## MODULES"
+        ]
+    );
+    assert_eq!(
+        titles("1.7"),
+        [
+            "## BUILTINS",
+            "### ltrimstr",
+            "### leaf_paths",
+            "### splits",
+            "## MODULES"
+        ]
+    );
+    assert_eq!(
+        titles("1.9"),
+        ["## BUILTINS", "### ltrimstr", "### splits", "## MODULES"]
+    );
+    assert_eq!(
+        titles("v1.10.0"),
+        [
+            "## BUILTINS",
+            "### ltrimstr",
+            "### getpath",
+            "### splits",
+            "### tojson",
+            "## MODULES"
+        ]
+    );
+}
+
+#[test]
+fn entries_nest_recursively() {
+    let yaml = r#"headline: jq
+manpage_intro: |
+  # jq(1) -- Command-line JSON processor
+body: ""
+manpage_epilogue: ""
+sections:
+  - title: Builtins
+    entries:
+      - title: Paths
+        body: Path functions.
+        entries:
+          - title: getpath
+            body: Gets a path.
+            entries:
+              - title: Errors
+                body: Raises.
+"#;
+    mandate::validate_yaml_with_schema_str(yaml, mandate::BUILTIN_SCHEMA).expect("valid");
+    let markdown = convert_yaml_to_markdown(yaml).expect("convert yaml");
+    assert!(markdown.contains("### Paths\n"));
+    assert!(markdown.contains("#### getpath\n"));
+    assert!(markdown.contains("##### Errors\n"));
+
+    let invalid = yaml.replace("                body: Raises.\n", "");
+    let err = mandate::validate_yaml_with_schema_str(&invalid, mandate::BUILTIN_SCHEMA)
+        .expect_err("nested entries need a body");
+    assert!(matches!(err, mandate::MandateError::Schema(_)));
+}